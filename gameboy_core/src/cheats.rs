@@ -0,0 +1,232 @@
+// Game Genie codes replace a byte whenever it's read from the rom,
+// GameShark codes write a byte to ram at the start of every frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheatKind {
+    GameGenie {
+        address: u16,
+        value: u8,
+        // only replace the byte when the rom has this value, that way
+        // the code only affects the rom bank it was made for
+        compare: Option<u8>,
+    },
+    GameShark {
+        // 0x0X selects cartridge ram bank X for A000-BFFF, 0x9X selects the
+        // CGB's work ram bank X for D000-DFFF, other values use the current bank
+        bank: u8,
+        address: u16,
+        value: u8,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Cheat {
+    pub code: String,
+    pub description: String,
+    pub enabled: bool,
+    pub kind: CheatKind,
+}
+
+impl Cheat {
+    // Game Genie codes look like ABC-DEF or ABC-DEF-GHI,
+    // GameShark codes are 8 hex digits, TTVVLLHH
+    pub fn from_code(code: &str) -> Result<Cheat, String> {
+        let code = code.trim();
+        let digits = code
+            .chars()
+            .filter(|&c| c != '-')
+            .map(|c| {
+                c.to_digit(16)
+                    .map(|digit| digit as u8)
+                    .ok_or_else(|| format!("Invalid cheat code: {}", code))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        let is_game_genie = code.contains('-');
+
+        let kind = match digits.len() {
+            6 | 9 if is_game_genie => Cheat::decode_game_genie(&digits),
+            8 if !is_game_genie => Cheat::decode_game_shark(&digits),
+            _ => return Err(format!("Invalid cheat code: {}", code)),
+        };
+        match kind {
+            CheatKind::GameGenie { address, .. } if address >= 0x8000 => {
+                return Err(format!("Game Genie code outside of the rom: {}", code));
+            }
+            CheatKind::GameShark { address, .. }
+                if !(0xA000..=0xDFFF).contains(&address)
+                    && !(0xFF80..=0xFFFE).contains(&address) =>
+            {
+                return Err(format!("GameShark code outside of ram: {}", code));
+            }
+            _ => {}
+        }
+        Ok(Cheat {
+            code: code.to_uppercase(),
+            description: String::new(),
+            enabled: true,
+            kind,
+        })
+    }
+
+    fn decode_game_genie(digits: &[u8]) -> CheatKind {
+        let value = (digits[0] << 4) | digits[1];
+        let address = (u16::from(digits[5] ^ 0x0F) << 12)
+            | (u16::from(digits[2]) << 8)
+            | (u16::from(digits[3]) << 4)
+            | u16::from(digits[4]);
+        // H isn't used, it's only there to make the codes harder to guess
+        let compare = if digits.len() == 9 {
+            Some(((digits[6] << 4) | digits[8]).rotate_right(2) ^ 0xBA)
+        } else {
+            None
+        };
+        CheatKind::GameGenie {
+            address,
+            value,
+            compare,
+        }
+    }
+
+    fn decode_game_shark(digits: &[u8]) -> CheatKind {
+        let byte = |i: usize| (digits[i] << 4) | digits[i + 1];
+        CheatKind::GameShark {
+            bank: byte(0),
+            value: byte(2),
+            address: u16::from(byte(4)) | (u16::from(byte(6)) << 8),
+        }
+    }
+}
+
+// cheat files have one code per line, optionally followed by a description.
+// lines starting with # are comments, and codes starting with - are disabled
+pub fn parse_cheat_file(contents: &str) -> Result<Vec<Cheat>, String> {
+    let mut cheats = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (enabled, line) = match line.strip_prefix('-') {
+            Some(line) => (false, line.trim_start()),
+            None => (true, line),
+        };
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let code = parts.next().unwrap_or("");
+        let mut cheat =
+            Cheat::from_code(code).map_err(|e| format!("line {}: {}", line_number + 1, e))?;
+        cheat.description = parts.next().unwrap_or("").trim().to_string();
+        cheat.enabled = enabled;
+        cheats.push(cheat);
+    }
+    Ok(cheats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_genie() {
+        assert_eq!(
+            Cheat::from_code("00A-17B").unwrap().kind,
+            CheatKind::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: None,
+            }
+        );
+        assert_eq!(
+            Cheat::from_code("00A-17B-C49").unwrap().kind,
+            CheatKind::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: Some(0xC8),
+            }
+        );
+        // E_A compares against 0x00, the middle digit is ignored
+        assert_eq!(
+            Cheat::from_code("C3A-15F-E6A").unwrap().kind,
+            CheatKind::GameGenie {
+                address: 0x0A15,
+                value: 0xC3,
+                compare: Some(0x00),
+            }
+        );
+    }
+
+    #[test]
+    fn game_shark() {
+        assert_eq!(
+            Cheat::from_code("01FF16D0").unwrap().kind,
+            CheatKind::GameShark {
+                bank: 0x01,
+                address: 0xD016,
+                value: 0xFF,
+            }
+        );
+        assert_eq!(
+            Cheat::from_code("916380FF").unwrap().kind,
+            CheatKind::GameShark {
+                bank: 0x91,
+                address: 0xFF80,
+                value: 0x63,
+            }
+        );
+    }
+
+    #[test]
+    fn codes_are_uppercased() {
+        assert_eq!(
+            Cheat::from_code(" 00a-17b-c49 ").unwrap().code,
+            "00A-17B-C49"
+        );
+    }
+
+    #[test]
+    fn invalid_codes() {
+        for code in &[
+            "",
+            "ZZZ-ZZZ",
+            "00A-17",
+            "00A-17B-C4",
+            "0100-16D0",
+            "01FF16D",
+            "01FF16D00",
+        ] {
+            assert!(Cheat::from_code(code).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn codes_outside_of_their_memory() {
+        assert_eq!(
+            Cheat::from_code("00A-177").unwrap_err(),
+            "Game Genie code outside of the rom: 00A-177"
+        );
+        assert_eq!(
+            Cheat::from_code("01FF0080").unwrap_err(),
+            "GameShark code outside of ram: 01FF0080"
+        );
+        assert!(Cheat::from_code("01FFFFFF").is_err());
+    }
+
+    #[test]
+    fn cheat_file() {
+        let contents = "# lives\n00A-17B-C49 Infinite lives\n\n-01FF16D0   Max health \n";
+        let cheats = parse_cheat_file(contents).unwrap();
+        assert_eq!(cheats.len(), 2);
+        assert_eq!(cheats[0].code, "00A-17B-C49");
+        assert_eq!(cheats[0].description, "Infinite lives");
+        assert!(cheats[0].enabled);
+        assert_eq!(cheats[1].code, "01FF16D0");
+        assert_eq!(cheats[1].description, "Max health");
+        assert!(!cheats[1].enabled);
+    }
+
+    #[test]
+    fn cheat_file_errors() {
+        assert_eq!(
+            parse_cheat_file("00A-17B\nXYZ lives").unwrap_err(),
+            "line 2: Invalid cheat code: XYZ"
+        );
+    }
+}
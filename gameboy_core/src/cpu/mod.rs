@@ -1,4 +1,4 @@
-mod registers;
+pub mod registers;
 
 use self::registers::flag::Flag;
 use self::registers::Registers;
@@ -74,6 +74,18 @@ impl Cpu {
         }
     }
 
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn get_registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn get_registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
     pub fn are_interrupts_enabled(&self) -> bool {
         self.interrupt_enabled
     }
@@ -119,6 +131,13 @@ impl Cpu {
 
             let opcode = self.get_n(memory);
             self.instruction_cycle += INSTRUCTION_TIMINGS[opcode as usize];
+            // loads and stores access memory in their last M-cycle
+            let access_delay = self.instruction_cycle - 4;
+            memory.set_sound_access_delay(if self.cgb_speed {
+                access_delay / 2
+            } else {
+                access_delay
+            });
             self.execute_opcode(opcode, memory);
         } else {
             self.instruction_cycle = 4;
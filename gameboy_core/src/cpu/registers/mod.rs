@@ -1,6 +1,6 @@
 pub mod flag;
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Registers {
     pub a: u8,
     pub f: flag::Flag,
@@ -0,0 +1,354 @@
+use crate::cpu::registers::Registers;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+// a bank of None matches every bank, the bank is only
+// checked for addresses in the switchable rom area
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Breakpoint {
+    pub bank: Option<usize>,
+    pub address: u16,
+}
+
+impl Breakpoint {
+    pub fn new(bank: Option<usize>, address: u16) -> Breakpoint {
+        Breakpoint { bank, address }
+    }
+
+    pub fn matches(&self, rom_bank: usize, address: u16) -> bool {
+        if self.address != address {
+            return false;
+        }
+        match (self.bank, address) {
+            (Some(bank), 0x4000..=0x7FFF) => bank == rom_bank,
+            (Some(bank), 0x0000..=0x3FFF) => bank == 0,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Watchpoint {
+    pub address: u16,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(address: u16, kind: WatchKind) -> Watchpoint {
+        Watchpoint { address, kind }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BreakReason {
+    Breakpoint(Breakpoint),
+    // value is the value being written, for reads it's None
+    Watchpoint {
+        watchpoint: Watchpoint,
+        value: Option<u8>,
+    },
+    Step,
+}
+
+// what the debugger does with the next instructions
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RunMode {
+    Running,
+    StepInto,
+    // stops when the instruction after a call is reached at the same stack depth
+    StepOver { address: u16, stack_pointer: u16 },
+    // stops after a return leaves the current function
+    StepOut { stack_pointer: u16 },
+    RunTo(Breakpoint),
+}
+
+// a call or interrupt that hasn't returned yet, the bank is
+// the rom bank that was mapped when it happened
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CallFrame {
+    pub caller: u16,
+    pub target: u16,
+    pub bank: usize,
+    pub stack_pointer: u16,
+    pub interrupt: bool,
+}
+
+// where the emulated code is, shared so a panic hook on the emulator's thread
+// can read it. it's only kept up to date while the call stack is tracked
+#[derive(Default)]
+pub struct CrashContext {
+    pc: Cell<u16>,
+    rom_bank: Cell<usize>,
+    call_stack: RefCell<Vec<CallFrame>>,
+}
+
+impl CrashContext {
+    // the instruction that was running
+    pub fn get_pc(&self) -> u16 {
+        self.pc.get()
+    }
+
+    pub fn get_rom_bank(&self) -> usize {
+        self.rom_bank.get()
+    }
+
+    // empty if the panic happened while the call stack was being changed
+    pub fn get_call_stack(&self) -> Vec<CallFrame> {
+        match self.call_stack.try_borrow() {
+            Ok(call_stack) => call_stack.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+// code that never returns from its calls shouldn't grow the stack forever
+const MAX_CALL_DEPTH: usize = 256;
+
+const CALL_OPCODES: [u8; 5] = [0xCD, 0xC4, 0xCC, 0xD4, 0xDC];
+const RST_OPCODES: [u8; 8] = [0xC7, 0xCF, 0xD7, 0xDF, 0xE7, 0xEF, 0xF7, 0xFF];
+const RETURN_OPCODES: [u8; 6] = [0xC9, 0xD9, 0xC0, 0xC8, 0xD0, 0xD8];
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    run_mode: RunMode,
+    break_reason: Option<BreakReason>,
+    // the break is reported on the next step when another result got in the way
+    pending_break: bool,
+    // continuing from a breakpoint shouldn't stop at it again right away
+    resume_address: Option<u16>,
+    // following calls costs a little on every instruction, so it's opt in
+    tracking_call_stack: bool,
+    // also holds the call stack
+    crash_context: Rc<CrashContext>,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            run_mode: RunMode::Running,
+            break_reason: None,
+            pending_break: false,
+            resume_address: None,
+            tracking_call_stack: false,
+            crash_context: Rc::new(CrashContext::default()),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|&other| other != breakpoint);
+    }
+
+    pub fn get_breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn get_break_reason(&self) -> Option<BreakReason> {
+        self.break_reason
+    }
+
+    pub fn step_into(&mut self) {
+        self.run_mode = RunMode::StepInto;
+    }
+
+    // calls and rsts are run until they return, anything else is a single step
+    pub fn step_over(&mut self, registers: &Registers, opcode: u8) {
+        let length = if CALL_OPCODES.contains(&opcode) {
+            3
+        } else if RST_OPCODES.contains(&opcode) {
+            1
+        } else {
+            self.run_mode = RunMode::StepInto;
+            return;
+        };
+        self.run_mode = RunMode::StepOver {
+            address: registers.pc.wrapping_add(length),
+            stack_pointer: registers.sp,
+        };
+    }
+
+    pub fn step_out(&mut self, registers: &Registers) {
+        self.run_mode = RunMode::StepOut {
+            stack_pointer: registers.sp,
+        };
+    }
+
+    pub fn run_to(&mut self, breakpoint: Breakpoint) {
+        self.run_mode = RunMode::RunTo(breakpoint);
+    }
+
+    pub fn resume(&mut self) {
+        self.run_mode = RunMode::Running;
+    }
+
+    // stops before the next instruction
+    pub fn request_break(&mut self) {
+        self.break_now(BreakReason::Step);
+        self.pending_break = true;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.pending_break || self.run_mode != RunMode::Running || !self.breakpoints.is_empty()
+    }
+
+    pub fn take_pending_break(&mut self) -> bool {
+        let pending_break = self.pending_break;
+        self.pending_break = false;
+        pending_break
+    }
+
+    // checked before an instruction runs
+    pub fn should_break_before(&mut self, rom_bank: usize, registers: &Registers) -> bool {
+        let pc = registers.pc;
+        if self.resume_address.take() == Some(pc) {
+            return false;
+        }
+        let reason = match self.run_mode {
+            RunMode::RunTo(breakpoint) if breakpoint.matches(rom_bank, pc) => {
+                Some(BreakReason::Breakpoint(breakpoint))
+            }
+            RunMode::StepOver {
+                address,
+                stack_pointer,
+            } if address == pc && registers.sp >= stack_pointer => Some(BreakReason::Step),
+            _ => self
+                .breakpoints
+                .iter()
+                .find(|breakpoint| breakpoint.matches(rom_bank, pc))
+                .map(|&breakpoint| BreakReason::Breakpoint(breakpoint)),
+        };
+        match reason {
+            Some(reason) => {
+                self.break_now(reason);
+                self.resume_address = Some(pc);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // checked after an instruction ran, opcode is the instruction that ran
+    pub fn should_break_after(&mut self, registers: &Registers, opcode: u8) -> bool {
+        let reason = match self.run_mode {
+            RunMode::StepInto => Some(BreakReason::Step),
+            RunMode::StepOut { stack_pointer }
+                if RETURN_OPCODES.contains(&opcode) && registers.sp > stack_pointer =>
+            {
+                Some(BreakReason::Step)
+            }
+            _ => None,
+        };
+        match reason {
+            Some(reason) => {
+                self.break_now(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn watchpoint_hit(&mut self, watchpoint: Watchpoint, value: Option<u8>) {
+        self.break_now(BreakReason::Watchpoint { watchpoint, value });
+    }
+
+    pub fn set_pending_break(&mut self) {
+        self.pending_break = true;
+    }
+
+    pub fn get_call_stack(&self) -> Vec<CallFrame> {
+        self.crash_context.get_call_stack()
+    }
+
+    pub fn get_crash_context(&self) -> Rc<CrashContext> {
+        self.crash_context.clone()
+    }
+
+    pub fn is_tracking_call_stack(&self) -> bool {
+        self.tracking_call_stack
+    }
+
+    // the frames seen so far are dropped when tracking stops
+    pub fn set_tracking_call_stack(&mut self, tracking: bool) {
+        self.tracking_call_stack = tracking;
+        if !tracking {
+            self.crash_context.call_stack.borrow_mut().clear();
+        }
+    }
+
+    pub fn set_current_instruction(&mut self, pc: u16, rom_bank: usize) {
+        self.crash_context.pc.set(pc);
+        self.crash_context.rom_bank.set(rom_bank);
+    }
+
+    // frames are left once the stack pointer is back above them, that
+    // covers returns as well as code that drops its return address
+    pub fn update_call_stack(
+        &mut self,
+        previous_registers: &Registers,
+        registers: &Registers,
+        opcode: u8,
+        rom_bank: usize,
+    ) {
+        {
+            let mut call_stack = self.crash_context.call_stack.borrow_mut();
+            while let Some(frame) = call_stack.last() {
+                if frame.stack_pointer >= registers.sp {
+                    break;
+                }
+                call_stack.pop();
+            }
+        }
+        let is_call = CALL_OPCODES.contains(&opcode) || RST_OPCODES.contains(&opcode);
+        if is_call && registers.sp == previous_registers.sp.wrapping_sub(2) {
+            self.push_call_frame(CallFrame {
+                caller: previous_registers.pc,
+                target: registers.pc,
+                bank: rom_bank,
+                stack_pointer: registers.sp,
+                interrupt: false,
+            });
+        }
+    }
+
+    pub fn enter_interrupt(&mut self, caller: u16, registers: &Registers, rom_bank: usize) {
+        self.push_call_frame(CallFrame {
+            caller,
+            target: registers.pc,
+            bank: rom_bank,
+            stack_pointer: registers.sp,
+            interrupt: true,
+        });
+    }
+
+    fn push_call_frame(&mut self, frame: CallFrame) {
+        let mut call_stack = self.crash_context.call_stack.borrow_mut();
+        if call_stack.len() == MAX_CALL_DEPTH {
+            call_stack.remove(0);
+        }
+        call_stack.push(frame);
+    }
+
+    fn break_now(&mut self, reason: BreakReason) {
+        self.break_reason = Some(reason);
+        self.run_mode = RunMode::Running;
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
@@ -0,0 +1,339 @@
+use crate::symbols::SymbolTable;
+
+// decodes SM83 instructions into RGBDS syntax, relative jumps
+// are shown with their target address instead of the offset,
+// jump targets and memory operands use labels when there are some
+const REGISTERS: [&str; 8] = ["b", "c", "d", "e", "h", "l", "[hl]", "a"];
+const REGISTER_PAIRS: [&str; 4] = ["bc", "de", "hl", "sp"];
+const STACK_REGISTER_PAIRS: [&str; 4] = ["bc", "de", "hl", "af"];
+const CONDITIONS: [&str; 4] = ["nz", "z", "nc", "c"];
+const ALU_OPERATIONS: [&str; 8] = [
+    "add a,", "adc a,", "sub a,", "sbc a,", "and a,", "xor a,", "or a,", "cp a,",
+];
+const ACCUMULATOR_OPERATIONS: [&str; 8] =
+    ["rlca", "rrca", "rla", "rra", "daa", "cpl", "scf", "ccf"];
+const ROTATIONS: [&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+const INDIRECT_LOADS: [&str; 4] = ["[bc]", "[de]", "[hl+]", "[hl-]"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub address: u16,
+    pub length: u16,
+    pub text: String,
+}
+
+pub fn disassemble(
+    read_byte: impl Fn(u16) -> u8,
+    get_label: impl Fn(u16) -> Option<String>,
+    address: u16,
+) -> Instruction {
+    let operand = |address: u16| get_label(address).unwrap_or_else(|| format!("${:04X}", address));
+    let n = || read_byte(address.wrapping_add(1));
+    let nn = || {
+        u16::from(read_byte(address.wrapping_add(1)))
+            | (u16::from(read_byte(address.wrapping_add(2))) << 8)
+    };
+    let opcode = read_byte(address);
+    let x = opcode >> 6;
+    let y = usize::from((opcode >> 3) & 0x07);
+    let z = opcode & 0x07;
+    let p = y >> 1;
+    let q = y & 0x01;
+    let relative_target = || {
+        let offset = n() as i8;
+        address.wrapping_add(2).wrapping_add(offset as u16)
+    };
+
+    let (length, text) = match (x, z) {
+        (0, 0) => match y {
+            0 => (1, String::from("nop")),
+            1 => (3, format!("ld [${:04X}], sp", nn())),
+            // stop is followed by a byte that's skipped
+            2 => (2, String::from("stop")),
+            3 => (2, format!("jr {}", operand(relative_target()))),
+            _ => (
+                2,
+                format!("jr {}, {}", CONDITIONS[y - 4], operand(relative_target())),
+            ),
+        },
+        (0, 1) if q == 0 => (3, format!("ld {}, ${:04X}", REGISTER_PAIRS[p], nn())),
+        (0, 1) => (1, format!("add hl, {}", REGISTER_PAIRS[p])),
+        (0, 2) if q == 0 => (1, format!("ld {}, a", INDIRECT_LOADS[p])),
+        (0, 2) => (1, format!("ld a, {}", INDIRECT_LOADS[p])),
+        (0, 3) if q == 0 => (1, format!("inc {}", REGISTER_PAIRS[p])),
+        (0, 3) => (1, format!("dec {}", REGISTER_PAIRS[p])),
+        (0, 4) => (1, format!("inc {}", REGISTERS[y])),
+        (0, 5) => (1, format!("dec {}", REGISTERS[y])),
+        (0, 6) => (2, format!("ld {}, ${:02X}", REGISTERS[y], n())),
+        (0, _) => (1, String::from(ACCUMULATOR_OPERATIONS[y])),
+        (1, 6) if y == 6 => (1, String::from("halt")),
+        (1, _) => (
+            1,
+            format!("ld {}, {}", REGISTERS[y], REGISTERS[usize::from(z)]),
+        ),
+        (2, _) => (
+            1,
+            format!("{} {}", ALU_OPERATIONS[y], REGISTERS[usize::from(z)]),
+        ),
+        (_, 0) => match y {
+            0..=3 => (1, format!("ret {}", CONDITIONS[y])),
+            4 => (2, format!("ldh [$FF{:02X}], a", n())),
+            5 => (2, format!("add sp, {}", format_signed(n() as i8))),
+            6 => (2, format!("ldh a, [$FF{:02X}]", n())),
+            _ => (2, format!("ld hl, sp{}", format_signed_offset(n() as i8))),
+        },
+        (_, 1) if q == 0 => (1, format!("pop {}", STACK_REGISTER_PAIRS[p])),
+        (_, 1) => match p {
+            0 => (1, String::from("ret")),
+            1 => (1, String::from("reti")),
+            2 => (1, String::from("jp hl")),
+            _ => (1, String::from("ld sp, hl")),
+        },
+        (_, 2) => match y {
+            0..=3 => (3, format!("jp {}, {}", CONDITIONS[y], operand(nn()))),
+            4 => (1, String::from("ldh [c], a")),
+            5 => (3, format!("ld [{}], a", operand(nn()))),
+            6 => (1, String::from("ldh a, [c]")),
+            _ => (3, format!("ld a, [{}]", operand(nn()))),
+        },
+        (_, 3) => match y {
+            0 => (3, format!("jp {}", operand(nn()))),
+            1 => (2, disassemble_cb(n())),
+            6 => (1, String::from("di")),
+            7 => (1, String::from("ei")),
+            _ => (1, format!("db ${:02X}", opcode)),
+        },
+        (_, 4) if y <= 3 => (3, format!("call {}, {}", CONDITIONS[y], operand(nn()))),
+        (_, 5) if q == 0 => (1, format!("push {}", STACK_REGISTER_PAIRS[p])),
+        (_, 5) if p == 0 => (3, format!("call {}", operand(nn()))),
+        (_, 6) => (2, format!("{} ${:02X}", ALU_OPERATIONS[y], n())),
+        (_, 7) => (1, format!("rst ${:02X}", y * 8)),
+        // the opcodes the SM83 doesn't have
+        _ => (1, format!("db ${:02X}", opcode)),
+    };
+
+    Instruction {
+        address,
+        length,
+        text,
+    }
+}
+
+fn disassemble_cb(opcode: u8) -> String {
+    let y = usize::from((opcode >> 3) & 0x07);
+    let register = REGISTERS[usize::from(opcode & 0x07)];
+    match opcode >> 6 {
+        0 => format!("{} {}", ROTATIONS[y], register),
+        1 => format!("bit {}, {}", y, register),
+        2 => format!("res {}, {}", y, register),
+        _ => format!("set {}, {}", y, register),
+    }
+}
+
+fn format_signed(value: i8) -> String {
+    if value < 0 {
+        format!("-${:02X}", -i16::from(value))
+    } else {
+        format!("${:02X}", value)
+    }
+}
+
+fn format_signed_offset(value: i8) -> String {
+    if value < 0 {
+        format!(" - ${:02X}", -i16::from(value))
+    } else {
+        format!(" + ${:02X}", value)
+    }
+}
+
+// a whole rom bank as an .asm file, from start to end without following jumps,
+// so data in the bank shows up as instructions too
+pub fn disassemble_bank(rom: &[u8], bank: usize, symbols: &SymbolTable) -> String {
+    let bank_start = bank * 0x4000;
+    let bank_data = &rom[bank_start..std::cmp::min(bank_start + 0x4000, rom.len())];
+    let base_address: u16 = if bank == 0 { 0x0000 } else { 0x4000 };
+    let mut output = if bank == 0 {
+        String::from("SECTION \"ROM Bank $00\", ROM0[$0000]\n\n")
+    } else {
+        format!(
+            "SECTION \"ROM Bank ${:02X}\", ROMX[$4000], BANK[${:02X}]\n\n",
+            bank, bank
+        )
+    };
+
+    let read_byte = |address: u16| {
+        let offset = usize::from(address - base_address);
+        bank_data.get(offset).copied().unwrap_or(0x00)
+    };
+    // the bank mapped to 0x4000 isn't known when disassembling bank 0
+    let get_label = |address: u16| {
+        if bank == 0 && (0x4000..0x8000).contains(&address) {
+            None
+        } else {
+            symbols.get_label(bank, address).map(String::from)
+        }
+    };
+    let mut offset = 0;
+    while offset < bank_data.len() {
+        let address = base_address + offset as u16;
+        if let Some(label) = get_label(address) {
+            output.push_str(&format!("{}:\n", label));
+        }
+        let mut instruction = disassemble(read_byte, get_label, address);
+        // instructions can't run past the end of the bank
+        if offset + usize::from(instruction.length) > bank_data.len() {
+            instruction.length = 1;
+            instruction.text = format!("db ${:02X}", bank_data[offset]);
+        }
+        let bytes: Vec<String> = bank_data[offset..offset + usize::from(instruction.length)]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        output.push_str(&format!(
+            "    {:<24}; ${:04X}: {}\n",
+            instruction.text,
+            address,
+            bytes.join(" ")
+        ));
+        offset += usize::from(instruction.length);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disassemble_bytes(bytes: &[u8], address: u16) -> Instruction {
+        let read_byte = |index: u16| {
+            let offset = usize::from(index.wrapping_sub(address));
+            bytes.get(offset).copied().unwrap_or(0x00)
+        };
+        disassemble(read_byte, |_| None, address)
+    }
+
+    #[test]
+    fn every_opcode_group() {
+        let cases: &[(&[u8], u16, &str)] = &[
+            // x = 0
+            (&[0x00], 1, "nop"),
+            (&[0x08, 0x34, 0x12], 3, "ld [$1234], sp"),
+            (&[0x10, 0x00], 2, "stop"),
+            (&[0x01, 0x34, 0x12], 3, "ld bc, $1234"),
+            (&[0x39], 1, "add hl, sp"),
+            (&[0x22], 1, "ld [hl+], a"),
+            (&[0x3A], 1, "ld a, [hl-]"),
+            (&[0x03], 1, "inc bc"),
+            (&[0x1B], 1, "dec de"),
+            (&[0x34], 1, "inc [hl]"),
+            (&[0x3D], 1, "dec a"),
+            (&[0x06, 0x12], 2, "ld b, $12"),
+            (&[0x27], 1, "daa"),
+            // x = 1 and 2
+            (&[0x76], 1, "halt"),
+            (&[0x78], 1, "ld a, b"),
+            (&[0x96], 1, "sub a, [hl]"),
+            // x = 3
+            (&[0xC0], 1, "ret nz"),
+            (&[0xE0, 0x40], 2, "ldh [$FF40], a"),
+            (&[0xF0, 0x44], 2, "ldh a, [$FF44]"),
+            (&[0xD1], 1, "pop de"),
+            (&[0xC9], 1, "ret"),
+            (&[0xD9], 1, "reti"),
+            (&[0xE9], 1, "jp hl"),
+            (&[0xF9], 1, "ld sp, hl"),
+            (&[0xCA, 0x00, 0x40], 3, "jp z, $4000"),
+            (&[0xE2], 1, "ldh [c], a"),
+            (&[0xEA, 0x00, 0xC0], 3, "ld [$C000], a"),
+            (&[0xF2], 1, "ldh a, [c]"),
+            (&[0xFA, 0x00, 0xC0], 3, "ld a, [$C000]"),
+            (&[0xC3, 0x50, 0x01], 3, "jp $0150"),
+            (&[0xF3], 1, "di"),
+            (&[0xFB], 1, "ei"),
+            (&[0xDC, 0x00, 0x40], 3, "call c, $4000"),
+            (&[0xF5], 1, "push af"),
+            (&[0xCD, 0x00, 0x40], 3, "call $4000"),
+            (&[0xFE, 0x90], 2, "cp a, $90"),
+            (&[0xFF], 1, "rst $38"),
+            // the CB block
+            (&[0xCB, 0x19], 2, "rr c"),
+            (&[0xCB, 0x37], 2, "swap a"),
+            (&[0xCB, 0x7C], 2, "bit 7, h"),
+            (&[0xCB, 0x86], 2, "res 0, [hl]"),
+            (&[0xCB, 0xFF], 2, "set 7, a"),
+        ];
+        for &(bytes, length, text) in cases {
+            let instruction = disassemble_bytes(bytes, 0xC000);
+            assert_eq!(
+                (instruction.length, instruction.text.as_str()),
+                (length, text),
+                "{:02X?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn stack_pointer_offsets() {
+        assert_eq!(disassemble_bytes(&[0xE8, 0x02], 0).text, "add sp, $02");
+        assert_eq!(disassemble_bytes(&[0xE8, 0xFE], 0).text, "add sp, -$02");
+        assert_eq!(disassemble_bytes(&[0xF8, 0x05], 0).text, "ld hl, sp + $05");
+        assert_eq!(disassemble_bytes(&[0xF8, 0x80], 0).text, "ld hl, sp - $80");
+    }
+
+    #[test]
+    fn relative_jump_targets() {
+        assert_eq!(disassemble_bytes(&[0x18, 0xFE], 0x0150).text, "jr $0150");
+        assert_eq!(
+            disassemble_bytes(&[0x20, 0xFB], 0x0150).text,
+            "jr nz, $014D"
+        );
+        assert_eq!(disassemble_bytes(&[0x38, 0x10], 0x0150).text, "jr c, $0162");
+        assert_eq!(disassemble_bytes(&[0x18, 0x80], 0x0000).text, "jr $FF82");
+    }
+
+    #[test]
+    fn illegal_opcodes() {
+        for &opcode in &[
+            0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+        ] {
+            let instruction = disassemble_bytes(&[opcode], 0);
+            assert_eq!(instruction.length, 1);
+            assert_eq!(instruction.text, format!("db ${:02X}", opcode));
+        }
+    }
+
+    #[test]
+    fn jump_targets_use_labels() {
+        let read_byte = |address: u16| [0xCD, 0x50, 0x01][usize::from(address)];
+        let get_label = |address: u16| Some(format!("Label{:04X}", address));
+        assert_eq!(disassemble(read_byte, get_label, 0).text, "call Label0150");
+    }
+
+    #[test]
+    fn bank_end() {
+        let mut rom = vec![0x00; 0x8000];
+        // the call's operand would be in the next bank
+        rom[0x7FFC..].copy_from_slice(&[0x3E, 0x12, 0xCD, 0x34]);
+        let mut symbols = SymbolTable::new();
+        symbols.add_symbol(crate::symbols::Symbol {
+            bank: 1,
+            address: 0x4000,
+            name: String::from("Start"),
+        });
+        let output = disassemble_bank(&rom, 1, &symbols);
+        assert!(output
+            .starts_with("SECTION \"ROM Bank $01\", ROMX[$4000], BANK[$01]\n\nStart:\n    nop "));
+        let expected = [
+            format!("    {:<24}; $7FFC: 3E 12\n", "ld a, $12"),
+            format!("    {:<24}; $7FFE: CD\n", "db $CD"),
+            format!("    {:<24}; $7FFF: 34\n", "inc [hl]"),
+        ]
+        .concat();
+        assert!(
+            output.ends_with(&expected),
+            "{}",
+            &output[output.len() - 200..]
+        );
+    }
+}
@@ -2,20 +2,36 @@ pub mod step_result;
 pub mod traits;
 
 use self::step_result::StepResult;
-use self::traits::{PixelMapper, RTC};
+use self::traits::{Accelerometer, ImageSource, Infrared, PixelMapper, Rumble, Speaker, RTC};
+use crate::cheats::Cheat;
+use crate::cpu::registers::Registers;
 use crate::cpu::Cpu;
+use crate::debugger::{Debugger, Watchpoint};
 use crate::gpu::GPU;
 use crate::joypad::Controller;
 use crate::mmu::cartridge::Cartridge;
 use crate::mmu::interrupt::Interrupt;
 use crate::mmu::Memory;
+use crate::ram_search::RamSnapshot;
+use crate::sound::audio_channel::AudioChannel;
+use crate::sound::wav_writer::WavOutput;
+use crate::symbols::SymbolTable;
 use crate::timer::Timer;
+use crate::tracer::Tracer;
 
 pub struct Emulator {
     cpu: Cpu,
     gpu: GPU,
     timer: Timer,
     memory: Memory,
+    cycle_count: u64,
+    // vblanks can be hidden behind another StepResult, this counts all of them
+    frame_count: u64,
+    rumble: Option<Box<dyn Rumble>>,
+    rumbling: bool,
+    debugger: Debugger,
+    tracer: Option<Tracer>,
+    symbols: SymbolTable,
 }
 
 impl Emulator {
@@ -26,6 +42,13 @@ impl Emulator {
             gpu: GPU::new(is_cgb),
             timer: Timer::new(),
             memory: Memory::from_cartridge(cartridge, rtc, is_cgb),
+            cycle_count: 0,
+            frame_count: 0,
+            rumble: None,
+            rumbling: false,
+            debugger: Debugger::new(),
+            tracer: None,
+            symbols: SymbolTable::new(),
         }
     }
 
@@ -34,19 +57,109 @@ impl Emulator {
         system: &mut impl PixelMapper,
         controller: &mut Controller,
     ) -> StepResult {
+        let debugging = self.debugger.is_active();
+        if debugging {
+            if self.debugger.take_pending_break() {
+                return StepResult::Breakpoint;
+            }
+            let rom_bank = self.memory.get_rom_bank();
+            if self
+                .debugger
+                .should_break_before(rom_bank, self.cpu.get_registers())
+            {
+                return StepResult::Breakpoint;
+            }
+        }
+        let previous_registers = *self.cpu.get_registers();
+        let rom_bank = self.memory.get_rom_bank();
+        let tracking_call_stack = self.debugger.is_tracking_call_stack();
+        // only the debugger looks at the opcode, this saves a read every step
+        let opcode = if debugging || tracking_call_stack {
+            self.memory.read_byte(previous_registers.pc)
+        } else {
+            0x00
+        };
+        if tracking_call_stack {
+            self.debugger
+                .set_current_instruction(previous_registers.pc, rom_bank);
+        }
+
+        // a halted cpu doesn't run instructions, so there's nothing to trace
+        if let Some(ref mut tracer) = self.tracer {
+            if !self.cpu.is_halted() {
+                let memory = &self.memory;
+                let label = if tracer.is_showing_labels() {
+                    self.symbols.get_label(rom_bank, previous_registers.pc)
+                } else {
+                    None
+                };
+                tracer.trace(rom_bank, &previous_registers, label, |address| {
+                    memory.read_byte(address)
+                });
+            }
+        }
+
+        self.memory.set_watchpoints_armed(true);
         let cycles = self.cpu.step(&mut self.memory);
+        self.memory.set_watchpoints_armed(false);
+        if tracking_call_stack {
+            self.debugger.update_call_stack(
+                &previous_registers,
+                self.cpu.get_registers(),
+                opcode,
+                rom_bank,
+            );
+        }
+        let mut breakpoint = false;
+        if let Some((watchpoint, value)) = self.memory.take_watchpoint_hit() {
+            self.debugger.watchpoint_hit(watchpoint, value);
+            breakpoint = true;
+        }
+        if debugging
+            && self
+                .debugger
+                .should_break_after(self.cpu.get_registers(), opcode)
+        {
+            breakpoint = true;
+        }
+
+        self.cycle_count += cycles as u64;
         self.timer.update(cycles, &mut self.memory);
-        let audio_buffer_full = self.memory.get_sound_mut().step(cycles);
+        self.memory.step_mbc(cycles);
+        let audio_buffer_full = self.memory.step_sound(cycles);
         let vblank = self.gpu.step(cycles, &mut self.memory, system);
         controller.update(&mut self.memory);
         self.handle_interrupts();
+        self.update_rumble();
+        if vblank {
+            self.frame_count += 1;
+            self.memory.apply_game_shark_codes();
+        }
 
-        if audio_buffer_full {
+        let step_result = if audio_buffer_full {
             StepResult::AudioBufferFull
         } else if vblank {
             StepResult::VBlank
         } else {
             StepResult::Nothing
+        };
+        if !breakpoint {
+            step_result
+        } else if step_result == StepResult::Nothing {
+            StepResult::Breakpoint
+        } else {
+            self.debugger.set_pending_break();
+            step_result
+        }
+    }
+
+    fn update_rumble(&mut self) {
+        if let Some(ref mut rumble) = self.rumble {
+            let rumbling = self.memory.is_rumbling();
+            if rumbling != self.rumbling {
+                self.rumbling = rumbling;
+                rumble.set_rumble(rumbling, self.cycle_count);
+            }
         }
     }
 
@@ -57,6 +170,7 @@ impl Emulator {
     }
     fn process_interrupt(&mut self, interrupt: Interrupt) {
         if self.cpu.are_interrupts_enabled() {
+            let caller = self.cpu.get_registers().pc;
             self.cpu.disable_interrupts();
             match interrupt {
                 Interrupt::Vblank => self.cpu.rst_40(&mut self.memory),
@@ -66,10 +180,111 @@ impl Emulator {
                 Interrupt::Joypad => self.cpu.rst_60(&mut self.memory),
             }
             self.memory.remove_interrupt(interrupt);
+            if self.debugger.is_tracking_call_stack() {
+                let rom_bank = self.memory.get_rom_bank();
+                self.debugger
+                    .enter_interrupt(caller, self.cpu.get_registers(), rom_bank);
+            }
         }
         self.cpu.unhalt();
     }
 
+    pub fn set_rumble(&mut self, rumble: Box<dyn Rumble>) {
+        self.rumble = Some(rumble);
+    }
+
+    pub fn set_accelerometer(&mut self, accelerometer: Box<dyn Accelerometer>) {
+        self.memory.set_accelerometer(accelerometer);
+    }
+
+    pub fn set_infrared(&mut self, infrared: Box<dyn Infrared>) {
+        self.memory.set_infrared(infrared);
+    }
+
+    pub fn set_speaker(&mut self, speaker: Box<dyn Speaker>) {
+        self.memory.set_speaker(speaker);
+    }
+
+    pub fn set_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        self.memory.set_image_source(image_source);
+    }
+
+    pub fn get_cheats(&self) -> &[Cheat] {
+        self.memory.get_cheats()
+    }
+
+    pub fn get_cheats_mut(&mut self) -> &mut Vec<Cheat> {
+        self.memory.get_cheats_mut()
+    }
+
+    pub fn get_ram_snapshot(&self) -> RamSnapshot {
+        self.memory.get_ram_snapshot()
+    }
+
+    pub fn get_debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn get_debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    pub fn get_symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    pub fn get_memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn get_tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        std::mem::replace(&mut self.tracer, tracer)
+    }
+
+    pub fn get_registers(&self) -> &Registers {
+        self.cpu.get_registers()
+    }
+
+    pub fn get_registers_mut(&mut self) -> &mut Registers {
+        self.cpu.get_registers_mut()
+    }
+
+    pub fn get_watchpoints(&self) -> &[Watchpoint] {
+        self.memory.get_watchpoints()
+    }
+
+    pub fn get_watchpoints_mut(&mut self) -> &mut Vec<Watchpoint> {
+        self.memory.get_watchpoints_mut()
+    }
+
+    pub fn get_rom_bank(&self) -> usize {
+        self.memory.get_rom_bank()
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        self.memory.read_byte(address)
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        self.memory.write_byte(address, value);
+    }
+
+    pub fn get_cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
     pub fn get_cartridge(&self) -> &Cartridge {
         &self.memory.get_cartridge()
     }
@@ -85,4 +300,46 @@ impl Emulator {
     pub fn get_audio_buffer(&self) -> &[f32] {
         self.memory.get_sound().get_audio_buffer()
     }
+
+    pub fn set_audio_sample_rate(&mut self, sample_rate: u32) {
+        self.memory.get_sound_mut().set_sample_rate(sample_rate);
+    }
+
+    pub fn get_audio_sample_rate(&self) -> u32 {
+        self.memory.get_sound().get_sample_rate()
+    }
+
+    pub fn set_audio_channel_muted(&mut self, channel: AudioChannel, muted: bool) {
+        self.memory
+            .get_sound_mut()
+            .set_channel_muted(channel, muted);
+    }
+
+    pub fn set_audio_channel_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+        self.memory
+            .get_sound_mut()
+            .set_channel_soloed(channel, soloed);
+    }
+
+    pub fn set_audio_channel_buffers_enabled(&mut self, enabled: bool) {
+        self.memory
+            .get_sound_mut()
+            .set_channel_buffers_enabled(enabled);
+    }
+
+    pub fn get_audio_channel_buffer(&self, channel: AudioChannel) -> Option<&[f32]> {
+        self.memory.get_sound().get_channel_buffer(channel)
+    }
+
+    pub fn start_audio_recording(&mut self, output: Box<dyn WavOutput>) -> Result<(), String> {
+        self.memory.get_sound_mut().start_recording(output)
+    }
+
+    pub fn stop_audio_recording(&mut self) -> Result<(), String> {
+        self.memory.get_sound_mut().stop_recording()
+    }
+
+    pub fn is_recording_audio(&self) -> bool {
+        self.memory.get_sound().is_recording()
+    }
 }
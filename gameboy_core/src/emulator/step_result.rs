@@ -3,4 +3,6 @@ pub enum StepResult {
     VBlank,
     AudioBufferFull,
     Nothing,
+    // the debugger stopped, see Gameboy::get_break_reason
+    Breakpoint,
 }
@@ -10,3 +10,31 @@ pub trait RTC {
     // get the current unix timestamp in seconds
     fn get_current_time(&self) -> u64;
 }
+
+pub trait Rumble {
+    // called whenever the cartridge turns its motor on or off,
+    // cycle_count is the number of clocks emulated so far
+    fn set_rumble(&mut self, on: bool, cycle_count: u64);
+}
+
+pub trait Accelerometer {
+    // the tilt of the gameboy in g, x grows as the right side is tilted down
+    // and y grows as the bottom is tilted down
+    fn get_acceleration(&self) -> (f32, f32);
+}
+
+pub trait Infrared {
+    fn set_led(&mut self, on: bool);
+    fn is_receiving_light(&self) -> bool;
+}
+
+pub trait Speaker {
+    // tone is the HuC3's 4 bit tone number
+    fn play_tone(&mut self, tone: u8);
+}
+
+pub trait ImageSource {
+    // fills pixels with CAMERA_WIDTH * CAMERA_HEIGHT brightness values,
+    // row by row, 0 is black and 255 is white
+    fn capture(&mut self, pixels: &mut [u8]);
+}
@@ -0,0 +1,371 @@
+use super::bg_attributes::BgAttributes;
+use super::cgb_color::CGBColor;
+use super::color::Color;
+use super::lcd_control_flag::LcdControlFlag;
+use super::sprite_attributes::SpriteAttributes;
+use super::GPU;
+use crate::mmu::{self, Memory};
+
+// the debug views are drawn straight from memory, they don't
+// depend on where the GPU is in the frame
+const TILES_PER_ROW: usize = 16;
+const TILES_PER_BANK: usize = 384;
+const MAP_SIZE: usize = 256;
+const SPRITE_COUNT: usize = 40;
+const SPRITES_PER_ROW: usize = 8;
+const SWATCH_SIZE: usize = 8;
+const VIEWPORT_COLOR: [u8; 3] = [255, 0, 0];
+const WINDOW_COLOR: [u8; 3] = [0, 0, 255];
+// shown where sprites are transparent
+const TRANSPARENT_COLOR: [u8; 3] = [255, 0, 255];
+
+// an RGB24 image, rows of width pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl DebugImage {
+    fn new(width: usize, height: usize) -> DebugImage {
+        DebugImage {
+            width,
+            height,
+            pixels: vec![0xFF; width * height * 3],
+        }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color_bytes: [u8; 3]) {
+        let index = (y * self.width + x) * 3;
+        self.pixels[index..index + 3].copy_from_slice(&color_bytes);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileMapEntry {
+    pub tile: u8,
+    pub attributes: BgAttributes,
+}
+
+// the position is the one written to OAM, 8 pixels to the right
+// and 16 pixels below where the sprite shows on the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub index: usize,
+    pub x: u8,
+    pub y: u8,
+    pub tile: u8,
+    pub attributes: SpriteAttributes,
+}
+
+pub fn get_vram_banks(memory: &Memory) -> usize {
+    if memory.is_cgb() {
+        2
+    } else {
+        1
+    }
+}
+
+// every tile of every vram bank, the banks are side by side
+pub fn render_tile_data(memory: &Memory) -> DebugImage {
+    let banks = get_vram_banks(memory);
+    let rows = TILES_PER_BANK / TILES_PER_ROW;
+    let mut image = DebugImage::new(TILES_PER_ROW * 8 * banks, rows * 8);
+    let palette = get_background_palette(memory, 0);
+    for bank in 0..banks {
+        for tile in 0..TILES_PER_BANK {
+            let tile_x = bank * TILES_PER_ROW + tile % TILES_PER_ROW;
+            let tile_y = tile / TILES_PER_ROW;
+            for y in 0..8 {
+                for x in 0..8 {
+                    let address = 0x8000 + (tile * 16) as u16;
+                    let pixel = get_tile_pixel(memory, address, bank, x, y);
+                    image.set_pixel(tile_x * 8 + x, tile_y * 8 + y, palette[pixel]);
+                }
+            }
+        }
+    }
+    image
+}
+
+// map 0 is at 0x9800 and map 1 at 0x9C00
+pub fn get_tile_map(memory: &Memory, map: usize) -> Vec<TileMapEntry> {
+    let map_start = get_map_start(map);
+    (0..32 * 32)
+        .map(|offset| {
+            let address = map_start + offset as u16;
+            let attributes = if memory.is_cgb() {
+                BgAttributes::from_bits_truncate(memory.read_cgb_lcd_ram(address, 1))
+            } else {
+                BgAttributes::empty()
+            };
+            TileMapEntry {
+                tile: memory.read_cgb_lcd_ram(address, 0),
+                attributes,
+            }
+        })
+        .collect()
+}
+
+// the whole 256x256 map with the current tile set, the part the screen shows
+// is outlined if it's the background map, the window's part if it's the window map
+pub fn render_tile_map(memory: &Memory, map: usize) -> DebugImage {
+    let lcd_control = LcdControlFlag::from_bits_truncate(memory.load(mmu::LCD_CONTROL_INDEX));
+    let mut image = DebugImage::new(MAP_SIZE, MAP_SIZE);
+    for (index, entry) in get_tile_map(memory, map).iter().enumerate() {
+        let address = get_tile_address(lcd_control, entry.tile);
+        let bank = if entry.attributes.contains(BgAttributes::VRAM_BANK) {
+            1
+        } else {
+            0
+        };
+        let palette = get_background_palette(memory, usize::from(entry.attributes.bits() & 0x07));
+        for y in 0..8 {
+            for x in 0..8 {
+                let tile_x = if entry.attributes.contains(BgAttributes::XFLIP) {
+                    7 - x
+                } else {
+                    x
+                };
+                let tile_y = if entry.attributes.contains(BgAttributes::YFLIP) {
+                    7 - y
+                } else {
+                    y
+                };
+                let pixel = get_tile_pixel(memory, address, bank, tile_x, tile_y);
+                image.set_pixel((index % 32) * 8 + x, (index / 32) * 8 + y, palette[pixel]);
+            }
+        }
+    }
+
+    let background_map = if lcd_control.contains(LcdControlFlag::BACKGROUND_TILE_MAP) {
+        1
+    } else {
+        0
+    };
+    if map == background_map {
+        let scroll_x = usize::from(memory.load(mmu::SCROLL_X_INDEX));
+        let scroll_y = usize::from(memory.load(mmu::SCROLL_Y_INDEX));
+        draw_rectangle(&mut image, scroll_x, scroll_y, 160, 144, VIEWPORT_COLOR);
+    }
+
+    let window_map = if lcd_control.contains(LcdControlFlag::WINDOW_TILE_MAP) {
+        1
+    } else {
+        0
+    };
+    let window_x = usize::from(memory.load(mmu::WINDOW_X_INDEX));
+    let window_y = usize::from(memory.load(mmu::WINDOW_Y_INDEX));
+    if map == window_map
+        && lcd_control.contains(LcdControlFlag::WINDOW)
+        && window_x < 167
+        && window_y < 144
+    {
+        draw_rectangle(
+            &mut image,
+            0,
+            0,
+            167 - window_x,
+            144 - window_y,
+            WINDOW_COLOR,
+        );
+    }
+    image
+}
+
+pub fn get_sprites(memory: &Memory) -> Vec<Sprite> {
+    (0..SPRITE_COUNT)
+        .map(|index| {
+            let address = mmu::SPRITES_START_INDEX + (index * 4) as u16;
+            Sprite {
+                index,
+                y: memory.read_byte(address),
+                x: memory.read_byte(address + 1),
+                tile: memory.read_byte(address + 2),
+                attributes: SpriteAttributes::from_bits_truncate(memory.read_byte(address + 3)),
+            }
+        })
+        .collect()
+}
+
+// all the sprites in OAM order, 8 to a row, in the current sprite size
+pub fn render_sprites(memory: &Memory) -> DebugImage {
+    let lcd_control = LcdControlFlag::from_bits_truncate(memory.load(mmu::LCD_CONTROL_INDEX));
+    let tall_sprites = lcd_control.contains(LcdControlFlag::SPRITES_SIZE);
+    let sprite_height = if tall_sprites { 16 } else { 8 };
+    let rows = SPRITE_COUNT / SPRITES_PER_ROW;
+    let mut image = DebugImage::new(SPRITES_PER_ROW * 8, rows * sprite_height);
+    for sprite in get_sprites(memory) {
+        let tile = if tall_sprites {
+            sprite.tile & 0xFE
+        } else {
+            sprite.tile
+        };
+        let address = 0x8000 + u16::from(tile) * 16;
+        let bank = if memory.is_cgb() && sprite.attributes.contains(SpriteAttributes::VRAM_BANK) {
+            1
+        } else {
+            0
+        };
+        let palette = get_sprite_palette(memory, sprite.attributes);
+        let sprite_x = (sprite.index % SPRITES_PER_ROW) * 8;
+        let sprite_y = (sprite.index / SPRITES_PER_ROW) * sprite_height;
+        for y in 0..sprite_height {
+            for x in 0..8 {
+                let tile_x = if sprite.attributes.contains(SpriteAttributes::X_FLIP) {
+                    7 - x
+                } else {
+                    x
+                };
+                let tile_y = if sprite.attributes.contains(SpriteAttributes::Y_FLIP) {
+                    sprite_height - 1 - y
+                } else {
+                    y
+                };
+                let pixel = get_tile_pixel(memory, address, bank, tile_x, tile_y);
+                let color_bytes = if pixel == 0 {
+                    TRANSPARENT_COLOR
+                } else {
+                    palette[pixel]
+                };
+                image.set_pixel(sprite_x + x, sprite_y + y, color_bytes);
+            }
+        }
+    }
+    image
+}
+
+// one row per palette, the background palettes on the left and the sprite
+// palettes on the right, the original gameboy only has 1 and 2 of them
+pub fn render_palettes(memory: &Memory) -> DebugImage {
+    let mut image = DebugImage::new(SWATCH_SIZE * 9, SWATCH_SIZE * 8);
+    for palette_index in 0..8 {
+        if memory.is_cgb() || palette_index == 0 {
+            let palette = get_background_palette(memory, palette_index);
+            draw_palette(&mut image, 0, palette_index, palette);
+        }
+        if memory.is_cgb() || palette_index < 2 {
+            let palette = get_object_palette(memory, palette_index);
+            draw_palette(&mut image, SWATCH_SIZE * 5, palette_index, palette);
+        }
+    }
+    image
+}
+
+fn draw_palette(image: &mut DebugImage, left: usize, row: usize, palette: [[u8; 3]; 4]) {
+    for (color_index, &color_bytes) in palette.iter().enumerate() {
+        for y in 0..SWATCH_SIZE {
+            for x in 0..SWATCH_SIZE {
+                image.set_pixel(
+                    left + color_index * SWATCH_SIZE + x,
+                    row * SWATCH_SIZE + y,
+                    color_bytes,
+                );
+            }
+        }
+    }
+}
+
+fn get_map_start(map: usize) -> u16 {
+    if map == 0 {
+        0x9800
+    } else {
+        0x9C00
+    }
+}
+
+fn get_tile_address(lcd_control: LcdControlFlag, tile: u8) -> u16 {
+    if lcd_control.contains(LcdControlFlag::BACKGROUND_TILE_SET) {
+        0x8000 + u16::from(tile) * 16
+    } else {
+        (0x9000 + i32::from(tile as i8) * 16) as u16
+    }
+}
+
+// rows of 8x16 sprites continue into the next tile
+fn get_tile_pixel(memory: &Memory, address: u16, bank: usize, x: usize, y: usize) -> usize {
+    let row_address = address + (y * 2) as u16;
+    let byte1 = memory.read_cgb_lcd_ram(row_address, bank as i32);
+    let byte2 = memory.read_cgb_lcd_ram(row_address + 1, bank as i32);
+    let bit = 7 - x;
+    usize::from((byte1 >> bit) & 0x01) | (usize::from((byte2 >> bit) & 0x01) << 1)
+}
+
+fn get_background_palette(memory: &Memory, palette_index: usize) -> [[u8; 3]; 4] {
+    if memory.is_cgb() {
+        cgb_palette_to_bytes(memory.cgb_background_palettes[palette_index])
+    } else {
+        gb_palette_to_bytes(memory.load(mmu::BACKGROUND_PALETTE_INDEX))
+    }
+}
+
+fn get_sprite_palette(memory: &Memory, attributes: SpriteAttributes) -> [[u8; 3]; 4] {
+    if memory.is_cgb() {
+        get_object_palette(memory, usize::from(attributes.bits() & 0x07))
+    } else if attributes.contains(SpriteAttributes::PALETTE) {
+        get_object_palette(memory, 1)
+    } else {
+        get_object_palette(memory, 0)
+    }
+}
+
+fn get_object_palette(memory: &Memory, palette_index: usize) -> [[u8; 3]; 4] {
+    if memory.is_cgb() {
+        cgb_palette_to_bytes(memory.cgb_sprite_palettes[palette_index])
+    } else if palette_index == 1 {
+        gb_palette_to_bytes(memory.load(mmu::OBJECT_PALETTE_1_INDEX))
+    } else {
+        gb_palette_to_bytes(memory.load(mmu::OBJECT_PALETTE_0_INDEX))
+    }
+}
+
+fn cgb_palette_to_bytes(palette: [CGBColor; 4]) -> [[u8; 3]; 4] {
+    let mut bytes = [[0; 3]; 4];
+    for (color_bytes, &color) in bytes.iter_mut().zip(palette.iter()) {
+        let color = GPU::cgb_color_to_rgb_color(color);
+        *color_bytes = [color.red, color.green, color.blue];
+    }
+    bytes
+}
+
+fn gb_palette_to_bytes(palette: u8) -> [[u8; 3]; 4] {
+    let mut bytes = [[0; 3]; 4];
+    for (pixel, color_bytes) in bytes.iter_mut().enumerate() {
+        // same shades the frontends use
+        *color_bytes = match GPU::gb_color_from_palette(palette, pixel as u8) {
+            Color::White => [255, 255, 255],
+            Color::LightGray => [178, 178, 178],
+            Color::DarkGray => [102, 102, 102],
+            Color::Black => [0, 0, 0],
+        };
+    }
+    bytes
+}
+
+// the outline wraps around the edges of the map, like the scrolling does
+fn draw_rectangle(
+    image: &mut DebugImage,
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    color_bytes: [u8; 3],
+) {
+    for x in 0..width {
+        image.set_pixel((left + x) % MAP_SIZE, top % MAP_SIZE, color_bytes);
+        image.set_pixel(
+            (left + x) % MAP_SIZE,
+            (top + height - 1) % MAP_SIZE,
+            color_bytes,
+        );
+    }
+    for y in 0..height {
+        image.set_pixel(left % MAP_SIZE, (top + y) % MAP_SIZE, color_bytes);
+        image.set_pixel(
+            (left + width - 1) % MAP_SIZE,
+            (top + y) % MAP_SIZE,
+            color_bytes,
+        );
+    }
+}
@@ -1,8 +1,9 @@
-mod bg_attributes;
+pub mod bg_attributes;
 pub mod cgb_color;
 pub mod color;
+pub mod debug_view;
 pub mod lcd_control_flag;
-mod sprite_attributes;
+pub mod sprite_attributes;
 
 use self::bg_attributes::BgAttributes;
 use self::cgb_color::CGBColor;
@@ -0,0 +1,36 @@
+use crate::emulator::traits::Infrared;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// one end of an infrared link between two emulators,
+// each end sees the light from the other end's LED
+pub struct InfraredLink {
+    own_led: Rc<Cell<bool>>,
+    other_led: Rc<Cell<bool>>,
+}
+
+impl InfraredLink {
+    pub fn new_pair() -> (InfraredLink, InfraredLink) {
+        let first_led = Rc::new(Cell::new(false));
+        let second_led = Rc::new(Cell::new(false));
+        let first = InfraredLink {
+            own_led: first_led.clone(),
+            other_led: second_led.clone(),
+        };
+        let second = InfraredLink {
+            own_led: second_led,
+            other_led: first_led,
+        };
+        (first, second)
+    }
+}
+
+impl Infrared for InfraredLink {
+    fn set_led(&mut self, on: bool) {
+        self.own_led.set(on);
+    }
+
+    fn is_receiving_light(&self) -> bool {
+        self.other_led.get()
+    }
+}
@@ -1,29 +1,69 @@
 mod bit_utils;
 pub mod button;
+mod cheats;
 pub mod controller_event;
 mod cpu;
+mod debugger;
+mod disassembler;
 pub mod emulator;
 mod gpu;
+mod infrared;
 mod joypad;
 mod mmu;
+mod patch;
+mod ram_search;
+mod rom_archive;
 pub mod rtc;
+mod screenshot;
 pub mod sound;
+mod symbols;
+mod test_rom;
 mod timer;
+mod tracer;
 
 pub use crate::button::Button;
+pub use crate::cheats::{parse_cheat_file, Cheat, CheatKind};
 pub use crate::controller_event::ControllerEvent;
+pub use crate::cpu::registers::flag::Flag;
+pub use crate::cpu::registers::Registers;
+pub use crate::debugger::{
+    BreakReason, Breakpoint, CallFrame, CrashContext, WatchKind, Watchpoint,
+};
+pub use crate::disassembler::{disassemble_bank, Instruction};
 pub use crate::emulator::step_result::StepResult;
-pub use crate::emulator::traits::{PixelMapper, RTC};
+pub use crate::emulator::traits::{
+    Accelerometer, ImageSource, Infrared, PixelMapper, Rumble, Speaker, RTC,
+};
 
 use crate::emulator::Emulator;
+pub use crate::gpu::bg_attributes::BgAttributes;
 pub use crate::gpu::cgb_color::CGBColor;
 pub use crate::gpu::color::Color;
+use crate::gpu::debug_view;
+pub use crate::gpu::debug_view::{DebugImage, Sprite, TileMapEntry};
+pub use crate::gpu::sprite_attributes::SpriteAttributes;
+pub use crate::infrared::InfraredLink;
 pub use crate::joypad::Controller;
 pub use crate::mmu::cartridge::Cartridge;
+pub use crate::mmu::pocket_camera::{CAMERA_HEIGHT, CAMERA_WIDTH};
+pub use crate::patch::apply_patch;
+pub use crate::ram_search::{
+    RamCandidate, RamRegion, RamSearch, RamSnapshot, SearchFilter, ValueFormat, ValueSize,
+};
+pub use crate::rom_archive::extract_rom;
 pub use crate::rtc::Rtc;
+use crate::screenshot::{CapturingPixelMapper, FrameCapture};
+pub use crate::screenshot::{SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use crate::sound::audio_channel::AudioChannel;
+pub use crate::symbols::{parse_symbol_file, Symbol, SymbolTable};
+pub use crate::test_rom::TestRomResult;
+pub use crate::tracer::{TraceFilter, TraceHandle, Tracer};
+use std::io::{Seek, Write};
+use std::rc::Rc;
 pub struct Gameboy {
     emulator: Emulator,
     controller: Controller,
+    frame_capture: FrameCapture,
 }
 impl Gameboy {
     /// Loads game from rom. Needs a Real Time Clock
@@ -32,21 +72,312 @@ impl Gameboy {
         Ok(Gameboy {
             emulator: Emulator::from_cartridge(cartridge, rtc),
             controller: Controller::new(),
+            frame_capture: FrameCapture::new(),
         })
     }
     /// Run emulation step
     pub fn emulate(&mut self, system: &mut impl PixelMapper) -> emulator::step_result::StepResult {
-        self.emulator.emulate(system, &mut self.controller)
+        let frame_count = self.emulator.get_frame_count();
+        let mut pixel_mapper = CapturingPixelMapper::new(&mut self.frame_capture, system);
+        let step_result = self
+            .emulator
+            .emulate(&mut pixel_mapper, &mut self.controller);
+        // the frame can end on a step that returns another result
+        if self.emulator.get_frame_count() != frame_count {
+            self.frame_capture.finish_frame();
+        }
+        step_result
+    }
+    /// The last fully drawn frame, as RGB24 rows of SCREEN_WIDTH pixels
+    pub fn get_frame_buffer(&self) -> &[u8] {
+        self.frame_capture.get_frame()
+    }
+    /// Encodes the last fully drawn frame as a PNG at the native resolution
+    pub fn get_screenshot_png(&self) -> Result<Vec<u8>, String> {
+        self.frame_capture.encode_png()
+    }
+    /// Number of clocks emulated so far, at 4194304 Hz in both CPU speeds
+    pub fn get_cycle_count(&self) -> u64 {
+        self.emulator.get_cycle_count()
+    }
+    /// Number of frames the LCD has finished, a step can finish one without
+    /// returning StepResult::VBlank when the audio buffer filled up at the same time
+    pub fn get_frame_count(&self) -> u64 {
+        self.emulator.get_frame_count()
     }
     pub fn get_audio_buffer(&self) -> &[f32] {
         self.emulator.get_audio_buffer()
     }
+    /// Sets the rate the audio buffer is resampled to, in Hz
+    pub fn set_audio_sample_rate(&mut self, sample_rate: u32) {
+        self.emulator.set_audio_sample_rate(sample_rate)
+    }
+    pub fn get_audio_sample_rate(&self) -> u32 {
+        self.emulator.get_audio_sample_rate()
+    }
+    pub fn set_audio_channel_muted(&mut self, channel: AudioChannel, muted: bool) {
+        self.emulator.set_audio_channel_muted(channel, muted)
+    }
+    /// While any channel is soloed, only the soloed channels are heard
+    pub fn set_audio_channel_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+        self.emulator.set_audio_channel_soloed(channel, soloed)
+    }
+    /// Also write every channel into its own buffer, laid out like the audio buffer.
+    /// The channel buffers ignore mute and solo
+    pub fn set_audio_channel_buffers_enabled(&mut self, enabled: bool) {
+        self.emulator.set_audio_channel_buffers_enabled(enabled)
+    }
+    pub fn get_audio_channel_buffer(&self, channel: AudioChannel) -> Option<&[f32]> {
+        self.emulator.get_audio_channel_buffer(channel)
+    }
+    /// Starts writing the audio output to a wav file, at the current sample rate.
+    /// Changing the sample rate ends the recording
+    pub fn start_audio_recording<W: Write + Seek + 'static>(
+        &mut self,
+        output: W,
+    ) -> Result<(), String> {
+        self.emulator.start_audio_recording(Box::new(output))
+    }
+    /// Finishes the wav file, reports any error that happened while recording
+    pub fn stop_audio_recording(&mut self) -> Result<(), String> {
+        self.emulator.stop_audio_recording()
+    }
+    pub fn is_recording_audio(&self) -> bool {
+        self.emulator.is_recording_audio()
+    }
     pub fn get_cartridge(&self) -> &Cartridge {
         self.emulator.get_cartridge()
     }
     pub fn get_cartridge_mut(&mut self) -> &mut Cartridge {
         self.emulator.get_cartridge_mut()
     }
+    /// Gets told whenever a rumble cartridge turns its motor on or off
+    pub fn set_rumble(&mut self, rumble: Box<dyn Rumble>) {
+        self.emulator.set_rumble(rumble)
+    }
+    /// Gives tilt input to cartridges with an accelerometer
+    pub fn set_accelerometer(&mut self, accelerometer: Box<dyn Accelerometer>) {
+        self.emulator.set_accelerometer(accelerometer)
+    }
+    /// Connects the cartridge's infrared port, an InfraredLink pair lets two games talk
+    pub fn set_infrared(&mut self, infrared: Box<dyn Infrared>) {
+        self.emulator.set_infrared(infrared)
+    }
+    /// Plays the tones of cartridges with their own speaker, like the HuC3
+    pub fn set_speaker(&mut self, speaker: Box<dyn Speaker>) {
+        self.emulator.set_speaker(speaker)
+    }
+    /// Adds a Game Genie or GameShark code, returns its index
+    pub fn add_cheat(&mut self, cheat: Cheat) -> usize {
+        let cheats = self.emulator.get_cheats_mut();
+        cheats.push(cheat);
+        cheats.len() - 1
+    }
+    /// Removes the cheat at index, which moves the ones after it down by one
+    pub fn remove_cheat(&mut self, index: usize) -> Result<Cheat, String> {
+        let cheats = self.emulator.get_cheats_mut();
+        if index >= cheats.len() {
+            return Err(format!("No cheat at index {}", index));
+        }
+        Ok(cheats.remove(index))
+    }
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) -> Result<(), String> {
+        let cheat = self
+            .emulator
+            .get_cheats_mut()
+            .get_mut(index)
+            .ok_or_else(|| format!("No cheat at index {}", index))?;
+        cheat.enabled = enabled;
+        Ok(())
+    }
+    pub fn get_cheats(&self) -> &[Cheat] {
+        self.emulator.get_cheats()
+    }
+    pub fn get_registers(&self) -> &Registers {
+        self.emulator.get_registers()
+    }
+    pub fn get_registers_mut(&mut self) -> &mut Registers {
+        self.emulator.get_registers_mut()
+    }
+    /// Reads memory the way the CPU sees it, without triggering watchpoints
+    pub fn read_byte(&self, address: u16) -> u8 {
+        self.emulator.read_byte(address)
+    }
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        self.emulator.write_byte(address, value)
+    }
+    /// The result a blargg test rom left in cartridge ram, once it's finished
+    pub fn get_test_rom_result(&self) -> Option<TestRomResult> {
+        test_rom::read_test_rom_result(|address| self.emulator.read_byte(address))
+    }
+    /// Every tile of every vram bank, the banks side by side
+    pub fn render_tile_data(&self) -> DebugImage {
+        debug_view::render_tile_data(self.emulator.get_memory())
+    }
+    /// The tiles and attributes of map 0 (0x9800) or 1 (0x9C00)
+    pub fn get_tile_map(&self, map: usize) -> Vec<TileMapEntry> {
+        debug_view::get_tile_map(self.emulator.get_memory(), map)
+    }
+    /// Map 0 (0x9800) or 1 (0x9C00), with the screen's viewport and the window outlined
+    pub fn render_tile_map(&self, map: usize) -> DebugImage {
+        debug_view::render_tile_map(self.emulator.get_memory(), map)
+    }
+    /// The 40 sprites in OAM, with their attributes decoded
+    pub fn get_sprites(&self) -> Vec<Sprite> {
+        debug_view::get_sprites(self.emulator.get_memory())
+    }
+    pub fn render_sprites(&self) -> DebugImage {
+        debug_view::render_sprites(self.emulator.get_memory())
+    }
+    /// The background palettes on the left and the sprite palettes on the right
+    pub fn render_palettes(&self) -> DebugImage {
+        debug_view::render_palettes(self.emulator.get_memory())
+    }
+    /// Logs every instruction in the gameboy-doctor format, a running trace is finished first
+    pub fn start_trace(&mut self, tracer: Tracer) -> Result<(), String> {
+        self.stop_trace()?;
+        self.emulator.set_tracer(Some(tracer));
+        Ok(())
+    }
+    /// Returns the lines kept by a ring buffer trace, reports any error that happened while writing
+    pub fn stop_trace(&mut self) -> Result<Vec<String>, String> {
+        match self.emulator.set_tracer(None) {
+            Some(tracer) => tracer.finish(),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// The last instructions kept by a ring buffer trace, oldest first
+    pub fn get_trace_history(&self) -> Vec<String> {
+        match self.emulator.get_tracer() {
+            Some(tracer) => tracer.get_history(),
+            None => Vec::new(),
+        }
+    }
+    /// Decodes the instruction at the address, in the current memory map
+    pub fn disassemble(&self, address: u16) -> Instruction {
+        let rom_bank = self.get_rom_bank();
+        let symbols = self.emulator.get_symbols();
+        disassembler::disassemble(
+            |address| self.emulator.read_byte(address),
+            |address| symbols.get_label(rom_bank, address).map(String::from),
+            address,
+        )
+    }
+    /// Disassembles a whole rom bank, as the contents of an .asm file
+    pub fn disassemble_bank(&self, bank: usize) -> Result<String, String> {
+        if bank * 0x4000 >= self.get_cartridge().get_rom().len() {
+            return Err(format!("No rom bank {:02X}", bank));
+        }
+        Ok(disassemble_bank(
+            self.get_cartridge().get_rom(),
+            bank,
+            self.emulator.get_symbols(),
+        ))
+    }
+    /// Labels from a .sym file, used by the disassembly and traces
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.emulator.set_symbols(symbols)
+    }
+    pub fn get_symbols(&self) -> &SymbolTable {
+        self.emulator.get_symbols()
+    }
+    /// The address as the closest label before it, like PlayerUpdate+$12, in the current memory map
+    pub fn format_address(&self, address: u16) -> String {
+        self.emulator
+            .get_symbols()
+            .format_address(self.get_rom_bank(), address)
+    }
+    /// The calls and interrupts that haven't returned yet, the innermost last.
+    /// Empty unless the call stack is tracked
+    pub fn get_call_stack(&self) -> Vec<CallFrame> {
+        self.emulator.get_debugger().get_call_stack()
+    }
+    /// Follows calls, returns and interrupts, for get_call_stack and the crash context
+    pub fn set_call_stack_tracking(&mut self, tracking: bool) {
+        self.emulator
+            .get_debugger_mut()
+            .set_tracking_call_stack(tracking)
+    }
+    pub fn is_tracking_call_stack(&self) -> bool {
+        self.emulator.get_debugger().is_tracking_call_stack()
+    }
+    /// Where the emulated code is while the call stack is tracked,
+    /// a panic hook on the emulator's thread can read it
+    pub fn get_crash_context(&self) -> Rc<CrashContext> {
+        self.emulator.get_debugger().get_crash_context()
+    }
+    /// The rom bank mapped to 0x4000, breakpoints in that area are matched against it
+    pub fn get_rom_bank(&self) -> usize {
+        self.emulator.get_rom_bank()
+    }
+    /// Emulation stops with StepResult::Breakpoint before running the instruction
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.emulator.get_debugger_mut().add_breakpoint(breakpoint)
+    }
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.emulator
+            .get_debugger_mut()
+            .remove_breakpoint(breakpoint)
+    }
+    pub fn get_breakpoints(&self) -> &[Breakpoint] {
+        self.emulator.get_debugger().get_breakpoints()
+    }
+    /// Emulation stops with StepResult::Breakpoint after the instruction that accessed the address
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        let watchpoints = self.emulator.get_watchpoints_mut();
+        if !watchpoints.contains(&watchpoint) {
+            watchpoints.push(watchpoint);
+        }
+    }
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.emulator
+            .get_watchpoints_mut()
+            .retain(|&other| other != watchpoint);
+    }
+    pub fn get_watchpoints(&self) -> &[Watchpoint] {
+        self.emulator.get_watchpoints()
+    }
+    /// Why the last StepResult::Breakpoint happened
+    pub fn get_break_reason(&self) -> Option<BreakReason> {
+        self.emulator.get_debugger().get_break_reason()
+    }
+    /// Stops before the next instruction
+    pub fn request_break(&mut self) {
+        self.emulator.get_debugger_mut().request_break()
+    }
+    /// Runs a single instruction before stopping again
+    pub fn step_into(&mut self) {
+        self.emulator.get_debugger_mut().step_into()
+    }
+    /// Like step_into, but runs calls until they return
+    pub fn step_over(&mut self) {
+        let registers = *self.emulator.get_registers();
+        let opcode = self.emulator.read_byte(registers.pc);
+        self.emulator
+            .get_debugger_mut()
+            .step_over(&registers, opcode)
+    }
+    /// Runs until the current function returns
+    pub fn step_out(&mut self) {
+        let registers = *self.emulator.get_registers();
+        self.emulator.get_debugger_mut().step_out(&registers)
+    }
+    /// Runs until the address is reached, or another breakpoint is hit
+    pub fn run_to(&mut self, breakpoint: Breakpoint) {
+        self.emulator.get_debugger_mut().run_to(breakpoint)
+    }
+    /// Keeps running until a breakpoint or watchpoint is hit
+    pub fn resume(&mut self) {
+        self.emulator.get_debugger_mut().resume()
+    }
+    /// Copies the work ram, high ram and cartridge ram, to search with a RamSearch
+    pub fn get_ram_snapshot(&self) -> RamSnapshot {
+        self.emulator.get_ram_snapshot()
+    }
+    /// Feeds the Pocket Camera's sensor, a test pattern is used without one
+    pub fn set_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        self.emulator.set_image_source(image_source)
+    }
     pub fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
         self.emulator.set_ram_change_callback(f)
     }
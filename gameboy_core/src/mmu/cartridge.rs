@@ -7,6 +7,7 @@ pub struct Cartridge {
     ram_size: usize,
     has_rtc: bool,
     has_battery: bool,
+    has_rumble: bool,
     rom: Vec<u8>,
     ram: Vec<u8>,
     name: String,
@@ -14,67 +15,105 @@ pub struct Cartridge {
     is_cgb: bool,
     rtc: Rtc,
     last_time: u64,
+    clock_memory: Vec<u8>,
 }
 
 impl Cartridge {
     pub fn from_rom(rom: Vec<u8>) -> Result<Cartridge, String> {
-        let cartridge_type = i32::from(rom[0x0147]);
+        let header = Cartridge::get_header_offset(&rom);
+        let cartridge_type = i32::from(rom[header + 0x0147]);
         let mbc_type = match cartridge_type {
             0x00 | 0x08 | 0x09 => Ok(MbcType::RomOnly),
-            0x01 | 0x02 | 0x03 | 0xEA | 0xFF => Ok(MbcType::Mbc1),
+            0x01 | 0x02 | 0x03 | 0xEA => Ok(MbcType::Mbc1),
             0x05 | 0x06 => Ok(MbcType::Mbc2),
-            0x0F | 0x10 | 0x11 | 0x12 | 0x13 | 0xFC => Ok(MbcType::Mbc3),
+            0x0B..=0x0D => Ok(MbcType::Mmm01),
+            0x0F..=0x13 => Ok(MbcType::Mbc3),
             0x19 | 0x1A | 0x1B | 0x1C | 0x1D | 0x1E => Ok(MbcType::Mbc5),
+            0x20 => Ok(MbcType::Mbc6),
+            0x22 => Ok(MbcType::Mbc7),
+            0xFC => Ok(MbcType::PocketCamera),
+            0xFD => Ok(MbcType::Tama5),
+            0xFE => Ok(MbcType::Huc3),
+            0xFF => Ok(MbcType::Huc1),
             _ => Err(format!(
                 "Unsupported cartridge type: 0x{:02X}",
                 cartridge_type
             )),
         }?;
+        let mbc_type = match mbc_type {
+            MbcType::Mbc1 if Cartridge::is_mbc1_multicart(&rom) => MbcType::Mbc1Multicart,
+            _ => mbc_type,
+        };
 
         let rom_banks = std::cmp::max(Cartridge::pow2ceil(rom.len() / 0x4000), 2);
 
-        let ram_size = usize::from(rom[0x0149]);
+        let ram_size = usize::from(rom[header + 0x0149]);
+        // the MBC30 is an MBC3 with twice the rom and ram banks,
+        // the header can only tell them apart by their size
+        let mbc_type = match mbc_type {
+            MbcType::Mbc3 if rom_banks > 128 || ram_size == 0x5 => MbcType::Mbc30,
+            _ => mbc_type,
+        };
         let ram_banks = match ram_size {
             0x0 => Ok(0),
             0x1 => Ok(1),
             0x2 => Ok(1),
             0x3 => Ok(4),
             0x4 => Ok(16),
+            0x5 => Ok(8),
             _ => Err(format!("Unknown number of RAM banks: 0x{:02X}", ram_size)),
         }?;
 
         let has_rtc = match cartridge_type {
-            0x0F | 0x10 => true,
+            0x0F | 0x10 | 0xFD | 0xFE => true,
             _ => false,
         };
         let has_battery = match cartridge_type {
-            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x17 | 0x1E | 0x1B | 0x22 | 0xFD
-            | 0xFF => true,
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x17 | 0x1E | 0x1B | 0x20 | 0x22
+            | 0xFC | 0xFD | 0xFE | 0xFF => true,
             _ => false,
         };
 
+        let has_rumble = matches!(cartridge_type, 0x1C..=0x1E);
+
         let mut name = String::new();
-        let mut name_index = 0x0134;
-        while rom[name_index] != 0x00 && name_index < 0x0143 {
+        let mut name_index = header + 0x0134;
+        while rom[name_index] != 0x00 && name_index < header + 0x0143 {
             let c = rom[name_index] as char;
             name.push(c);
             name_index += 1;
         }
 
-        let is_cgb = rom[0x0143] == 0xC0 || rom[0x0143] == 0x80;
+        let is_cgb = rom[header + 0x0143] == 0xC0 || rom[header + 0x0143] == 0x80;
 
         let ram = match mbc_type {
             MbcType::Mbc2 => vec![0x0F; 0x200],
-            MbcType::Mbc5 => vec![0xFF; 0x20000],
+            MbcType::Mbc5 | MbcType::Mmm01 | MbcType::PocketCamera => vec![0xFF; 0x20000],
+            // 32KB of ram followed by the 1MB flash
+            MbcType::Mbc6 => vec![0xFF; 0x108000],
+            MbcType::Mbc30 => vec![0xFF; 0x10000],
+            // the MBC7 saves to a 256 byte EEPROM
+            MbcType::Mbc7 => vec![0xFF; 0x100],
+            MbcType::Tama5 => vec![0x00; 0x20],
             _ => vec![0xFF; 0x8000],
         };
 
+        // the HuC3 and TAMA5 clocks keep alarms and the like next to the time,
+        // it's saved along with the rtc data
+        let clock_memory = match mbc_type {
+            MbcType::Huc3 => vec![0x00; 0x100],
+            // the TC8521AP's pages 1 to 3, 13 nibbles each
+            MbcType::Tama5 => vec![0x00; 3 * 0x0D],
+            _ => Vec::new(),
+        };
+
         Ok(Cartridge {
             rom_banks,
             ram_banks,
             ram_size,
             has_rtc,
             has_battery,
+            has_rumble,
             rom,
             ram,
             name,
@@ -82,9 +121,36 @@ impl Cartridge {
             is_cgb,
             rtc: Rtc::new(),
             last_time: 0,
+            clock_memory,
         })
     }
 
+    // MMM01 carts start up in the menu at the end of the rom,
+    // so that's where the header that describes the cart is
+    fn get_header_offset(rom: &[u8]) -> usize {
+        if rom.len() > 0x8000 {
+            let menu = rom.len() - 0x8000;
+            if let 0x0B..=0x0D = rom[menu + 0x0147] {
+                return menu;
+            }
+        }
+        0
+    }
+
+    // multicarts are 8 Mbit carts where the start of every game,
+    // every 16 banks, has its own copy of the nintendo logo
+    fn is_mbc1_multicart(rom: &[u8]) -> bool {
+        if rom.len() != 0x100000 {
+            return false;
+        }
+        let logo = &rom[0x0104..0x0134];
+        let repeated_logos = (1..4)
+            .map(|game| game * 0x40000 + 0x0104)
+            .filter(|&logo_index| &rom[logo_index..logo_index + logo.len()] == logo)
+            .count();
+        repeated_logos > 0
+    }
+
     fn pow2ceil(i: usize) -> usize {
         let mut i = i - 1;
         i |= i >> 1;
@@ -115,6 +181,22 @@ impl Cartridge {
         self.has_battery
     }
 
+    pub fn has_rumble(&self) -> bool {
+        self.has_rumble
+    }
+
+    pub fn has_accelerometer(&self) -> bool {
+        matches!(self.mbc_type, MbcType::Mbc7)
+    }
+
+    pub fn has_infrared(&self) -> bool {
+        matches!(self.mbc_type, MbcType::Huc1 | MbcType::Huc3)
+    }
+
+    pub fn has_camera(&self) -> bool {
+        matches!(self.mbc_type, MbcType::PocketCamera)
+    }
+
     pub fn get_rom(&self) -> &[u8] {
         self.rom.as_ref()
     }
@@ -155,4 +237,18 @@ impl Cartridge {
         self.rtc.days_high = rtc.days_high;
         self.last_time = last_time;
     }
+
+    pub fn get_clock_memory(&self) -> &[u8] {
+        &self.clock_memory
+    }
+
+    pub fn get_clock_memory_mut(&mut self) -> &mut [u8] {
+        &mut self.clock_memory
+    }
+
+    // a save that doesn't match the cartridge's clock only fills what fits
+    pub fn set_clock_memory(&mut self, clock_memory: &[u8]) {
+        let length = std::cmp::min(clock_memory.len(), self.clock_memory.len());
+        self.clock_memory[..length].copy_from_slice(&clock_memory[..length]);
+    }
 }
@@ -0,0 +1,183 @@
+use crate::bit_utils;
+
+// 93LC56 serial EEPROM in 16 bit mode, 128 words.
+// every command starts with a 1 bit, followed by a 2 bit opcode and an 8 bit address,
+// bits are shifted in on the rising edge of the clock while chip select is high
+const WORDS: usize = 128;
+const COMMAND_BITS: u8 = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Command,
+    Read,
+    Write,
+    WriteAll,
+    Done,
+}
+
+pub struct Eeprom {
+    state: State,
+    chip_select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+    write_enabled: bool,
+    shift_register: u16,
+    bit_count: u8,
+    address: usize,
+}
+
+impl Eeprom {
+    pub fn new() -> Eeprom {
+        Eeprom {
+            state: State::Idle,
+            chip_select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+            write_enabled: false,
+            shift_register: 0,
+            bit_count: 0,
+            address: 0,
+        }
+    }
+
+    pub fn read_byte(&self) -> u8 {
+        let chip_select = if self.chip_select { 1 } else { 0 };
+        let clock = if self.clock { 1 } else { 0 };
+        let data_in = if self.data_in { 1 } else { 0 };
+        let data_out = if self.data_out { 1 } else { 0 };
+        (chip_select << 7) | (clock << 6) | (data_in << 1) | data_out
+    }
+
+    // the words are stored big endian in the cartridge's ram,
+    // returns the ram addresses that were changed
+    pub fn write_byte(&mut self, value: u8, ram: &mut [u8]) -> Vec<usize> {
+        let chip_select = bit_utils::is_set(value, 7);
+        let clock = bit_utils::is_set(value, 6);
+        self.data_in = bit_utils::is_set(value, 1);
+
+        let mut changed = Vec::new();
+        if !chip_select {
+            // deselecting the chip ends any command, it's always ready again right away
+            self.state = State::Idle;
+            self.data_out = true;
+        } else if clock && !self.clock {
+            changed = self.clock_bit(ram);
+        }
+        self.chip_select = chip_select;
+        self.clock = clock;
+        changed
+    }
+
+    fn clock_bit(&mut self, ram: &mut [u8]) -> Vec<usize> {
+        let bit = if self.data_in { 1 } else { 0 };
+        match self.state {
+            State::Idle => {
+                if self.data_in {
+                    self.state = State::Command;
+                    self.shift_register = 0;
+                    self.bit_count = 0;
+                }
+                Vec::new()
+            }
+            State::Command => {
+                self.shift_register = (self.shift_register << 1) | bit;
+                self.bit_count += 1;
+                if self.bit_count == COMMAND_BITS {
+                    self.run_command(ram)
+                } else {
+                    Vec::new()
+                }
+            }
+            State::Read => {
+                // reads keep going into the next word for as long as the clock runs
+                let word = Eeprom::get_word(ram, self.address);
+                self.data_out = (word >> (15 - self.bit_count)) & 1 == 1;
+                self.bit_count += 1;
+                if self.bit_count == 16 {
+                    self.bit_count = 0;
+                    self.address = (self.address + 1) % WORDS;
+                }
+                Vec::new()
+            }
+            State::Write | State::WriteAll => {
+                self.shift_register = (self.shift_register << 1) | bit;
+                self.bit_count += 1;
+                if self.bit_count < 16 {
+                    return Vec::new();
+                }
+                let mut changed = Vec::new();
+                if self.write_enabled {
+                    if self.state == State::WriteAll {
+                        for address in 0..WORDS {
+                            changed.extend(Eeprom::set_word(ram, address, self.shift_register));
+                        }
+                    } else {
+                        changed.extend(Eeprom::set_word(ram, self.address, self.shift_register));
+                    }
+                }
+                self.state = State::Done;
+                changed
+            }
+            State::Done => Vec::new(),
+        }
+    }
+
+    fn run_command(&mut self, ram: &mut [u8]) -> Vec<usize> {
+        let opcode = (self.shift_register >> 8) & 0x03;
+        let address = self.shift_register as usize & 0xFF;
+        self.shift_register = 0;
+        self.bit_count = 0;
+        self.state = State::Done;
+
+        let mut changed = Vec::new();
+        match opcode {
+            0b10 => {
+                // a dummy 0 bit comes out before the data
+                self.address = address % WORDS;
+                self.data_out = false;
+                self.state = State::Read;
+            }
+            0b01 => {
+                self.address = address % WORDS;
+                self.state = State::Write;
+            }
+            0b11 => {
+                if self.write_enabled {
+                    changed.extend(Eeprom::set_word(ram, address % WORDS, 0xFFFF));
+                }
+            }
+            _ => match address >> 6 {
+                0b00 => self.write_enabled = false,
+                0b01 => self.state = State::WriteAll,
+                0b10 => {
+                    if self.write_enabled {
+                        for address in 0..WORDS {
+                            changed.extend(Eeprom::set_word(ram, address, 0xFFFF));
+                        }
+                    }
+                }
+                _ => self.write_enabled = true,
+            },
+        }
+        changed
+    }
+
+    fn get_word(ram: &[u8], address: usize) -> u16 {
+        (u16::from(ram[address * 2]) << 8) | u16::from(ram[address * 2 + 1])
+    }
+
+    fn set_word(ram: &mut [u8], address: usize, word: u16) -> [usize; 2] {
+        ram[address * 2] = (word >> 8) as u8;
+        ram[address * 2 + 1] = word as u8;
+        [address * 2, address * 2 + 1]
+    }
+}
+
+impl Default for Eeprom {
+    fn default() -> Eeprom {
+        Eeprom::new()
+    }
+}
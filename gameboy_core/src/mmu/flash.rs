@@ -0,0 +1,121 @@
+// MX29F008 style flash memory, commands are unlocked by writing 0xAA to 0x5555
+// and 0x55 to 0x2AAA, the chip ignores the address bits above those for commands
+const UNLOCK_ADDRESS_1: usize = 0x5555;
+const UNLOCK_ADDRESS_2: usize = 0x2AAA;
+const COMMAND_ADDRESS_MASK: usize = 0x7FFF;
+const SECTOR_SIZE: usize = 0x20000;
+const MANUFACTURER_ID: u8 = 0xC2;
+const DEVICE_ID: u8 = 0x81;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Ready,
+    Unlocked1,
+    Unlocked2,
+    Program,
+}
+
+pub struct Flash {
+    state: State,
+    erase_armed: bool,
+    in_id_mode: bool,
+}
+
+impl Flash {
+    pub fn new() -> Flash {
+        Flash {
+            state: State::Ready,
+            erase_armed: false,
+            in_id_mode: false,
+        }
+    }
+
+    pub fn read_byte(&self, address: usize, flash: &[u8]) -> u8 {
+        if self.in_id_mode {
+            match address & 0x01 {
+                0 => MANUFACTURER_ID,
+                _ => DEVICE_ID,
+            }
+        } else {
+            flash[address]
+        }
+    }
+
+    // programming and erasing finish right away,
+    // returns the flash addresses that were changed
+    pub fn write_byte(
+        &mut self,
+        address: usize,
+        value: u8,
+        flash: &mut [u8],
+        write_enabled: bool,
+    ) -> Vec<usize> {
+        let command_address = address & COMMAND_ADDRESS_MASK;
+        let mut changed = Vec::new();
+        if value == 0xF0 {
+            self.reset();
+            return changed;
+        }
+
+        match self.state {
+            State::Ready if value == 0xAA && command_address == UNLOCK_ADDRESS_1 => {
+                self.state = State::Unlocked1
+            }
+            State::Unlocked1 if value == 0x55 && command_address == UNLOCK_ADDRESS_2 => {
+                self.state = State::Unlocked2
+            }
+            State::Unlocked2 => {
+                self.state = State::Ready;
+                match value {
+                    0x30 if self.erase_armed => {
+                        if write_enabled {
+                            let sector_start = address - address % SECTOR_SIZE;
+                            changed.extend(Flash::erase(flash, sector_start, SECTOR_SIZE));
+                        }
+                        self.erase_armed = false;
+                    }
+                    _ if command_address != UNLOCK_ADDRESS_1 => self.erase_armed = false,
+                    0x10 if self.erase_armed => {
+                        if write_enabled {
+                            changed.extend(Flash::erase(flash, 0, flash.len()));
+                        }
+                        self.erase_armed = false;
+                    }
+                    0x80 => self.erase_armed = true,
+                    0x90 => self.in_id_mode = true,
+                    0xA0 => self.state = State::Program,
+                    _ => self.erase_armed = false,
+                }
+            }
+            State::Program => {
+                // programming can only clear bits, erasing sets them again
+                if write_enabled {
+                    flash[address] &= value;
+                    changed.push(address);
+                }
+                self.state = State::Ready;
+            }
+            _ => self.state = State::Ready,
+        }
+        changed
+    }
+
+    fn reset(&mut self) {
+        self.state = State::Ready;
+        self.erase_armed = false;
+        self.in_id_mode = false;
+    }
+
+    fn erase(flash: &mut [u8], start: usize, length: usize) -> std::ops::Range<usize> {
+        for byte in flash[start..start + length].iter_mut() {
+            *byte = 0xFF;
+        }
+        start..start + length
+    }
+}
+
+impl Default for Flash {
+    fn default() -> Flash {
+        Flash::new()
+    }
+}
@@ -0,0 +1,112 @@
+use super::cartridge::Cartridge;
+use super::mbc::Mbc;
+use crate::emulator::traits::Infrared;
+
+pub struct Huc1 {
+    cartridge: Cartridge,
+    selected_rom_bank: usize,
+    selected_eram_bank: usize,
+    // the ram window can be switched over to the infrared port
+    in_infrared_mode: bool,
+    infrared: Option<Box<dyn Infrared>>,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Huc1 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.selected_rom_bank * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xBFFF => {
+                if self.in_infrared_mode {
+                    let receiving = match self.infrared {
+                        Some(ref infrared) => infrared.is_receiving_light(),
+                        None => false,
+                    };
+                    if receiving {
+                        0xC1
+                    } else {
+                        0xC0
+                    }
+                } else if self.cartridge.get_ram_size() > 0 {
+                    let ram = self.cartridge.get_ram();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    ram[index as usize - 0xA000 + offset]
+                } else {
+                    0xFF
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x1FFF => self.in_infrared_mode = (value & 0x0F) == 0x0E,
+            0x2000..=0x3FFF => {
+                self.selected_rom_bank = usize::from(value & 0x3F);
+                self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
+            }
+            0x4000..=0x5FFF => {
+                let ram_banks = std::cmp::max(self.cartridge.get_ram_banks(), 1);
+                self.selected_eram_bank = usize::from(value & 0x03) & (ram_banks - 1);
+            }
+            0x6000..=0x7FFF => (),
+            0xA000..=0xBFFF => {
+                if self.in_infrared_mode {
+                    if let Some(ref mut infrared) = self.infrared {
+                        infrared.set_led(value & 0x01 == 0x01);
+                    }
+                } else if self.cartridge.get_ram_size() > 0 {
+                    let ram = self.cartridge.get_ram_mut();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    let address = index as usize - 0xA000 + offset;
+                    ram[address] = value;
+
+                    (self.ram_change_callback)(address, value);
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+
+    fn set_infrared(&mut self, infrared: Box<dyn Infrared>) {
+        self.infrared = Some(infrared);
+    }
+}
+
+impl Huc1 {
+    pub fn new(cartridge: Cartridge) -> Huc1 {
+        Huc1 {
+            cartridge,
+            selected_rom_bank: 1,
+            selected_eram_bank: 0,
+            in_infrared_mode: false,
+            infrared: None,
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use super::cartridge::Cartridge;
+use super::mbc::Mbc;
+use crate::emulator::traits::{Infrared, Speaker, RTC};
+use crate::rtc::Rtc;
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+const DAYS_MASK: u64 = 0xFFF;
+// where the clock is copied to and from in the rtc memory, as nibbles
+const TIME_ADDRESS: usize = 0x00;
+const TONE_ADDRESS: usize = 0x26;
+
+pub struct Huc3 {
+    cartridge: Cartridge,
+    selected_rom_bank: usize,
+    selected_eram_bank: usize,
+    mode: u8,
+    // the rtc is driven through commands, which work on a small nibble memory
+    // kept in the cartridge's clock memory
+    rtc: Box<dyn RTC>,
+    rtc_address: usize,
+    rtc_result: u8,
+    infrared: Option<Box<dyn Infrared>>,
+    speaker: Option<Box<dyn Speaker>>,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Huc3 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.selected_rom_bank * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xBFFF => match self.mode {
+                0x0 | 0xA if self.cartridge.get_ram_size() > 0 => {
+                    let ram = self.cartridge.get_ram();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    ram[index as usize - 0xA000 + offset]
+                }
+                0xC => self.rtc_result,
+                // the rtc is always ready for the next command
+                0xD => 0x01,
+                0xE => {
+                    let receiving = match self.infrared {
+                        Some(ref infrared) => infrared.is_receiving_light(),
+                        None => false,
+                    };
+                    if receiving {
+                        0xC1
+                    } else {
+                        0xC0
+                    }
+                }
+                _ => 0xFF,
+            },
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x1FFF => self.mode = value & 0x0F,
+            0x2000..=0x3FFF => {
+                self.selected_rom_bank = usize::from(value & 0x7F);
+                self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
+            }
+            0x4000..=0x5FFF => {
+                let ram_banks = std::cmp::max(self.cartridge.get_ram_banks(), 1);
+                self.selected_eram_bank = usize::from(value & 0x03) & (ram_banks - 1);
+            }
+            0x6000..=0x7FFF => (),
+            0xA000..=0xBFFF => match self.mode {
+                0xA if self.cartridge.get_ram_size() > 0 => {
+                    let ram = self.cartridge.get_ram_mut();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    let address = index as usize - 0xA000 + offset;
+                    ram[address] = value;
+
+                    (self.ram_change_callback)(address, value);
+                }
+                0xB => self.run_rtc_command(value),
+                0xE => {
+                    if let Some(ref mut infrared) = self.infrared {
+                        infrared.set_led(value & 0x01 == 0x01);
+                    }
+                }
+                _ => (),
+            },
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+
+    fn set_infrared(&mut self, infrared: Box<dyn Infrared>) {
+        self.infrared = Some(infrared);
+    }
+
+    fn set_speaker(&mut self, speaker: Box<dyn Speaker>) {
+        self.speaker = Some(speaker);
+    }
+}
+
+impl Huc3 {
+    pub fn new(mut cartridge: Cartridge, rtc: Box<dyn RTC>) -> Huc3 {
+        let (rtc_data, last_time) = cartridge.get_last_timestamp();
+        if last_time == 0 {
+            cartridge.set_last_timestamp(rtc_data, rtc.get_current_time());
+        }
+
+        Huc3 {
+            cartridge,
+            selected_rom_bank: 1,
+            selected_eram_bank: 0,
+            mode: 0,
+            rtc,
+            rtc_address: 0,
+            rtc_result: 0,
+            infrared: None,
+            speaker: None,
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    // the high nibble is the command, the low nibble its argument
+    fn run_rtc_command(&mut self, value: u8) {
+        let argument = value & 0x0F;
+        match value >> 4 {
+            0x1 => {
+                self.rtc_result = self.cartridge.get_clock_memory()[self.rtc_address];
+                self.rtc_address = (self.rtc_address + 1) & 0xFF;
+            }
+            0x3 => {
+                self.cartridge.get_clock_memory_mut()[self.rtc_address] = argument;
+                self.rtc_address = (self.rtc_address + 1) & 0xFF;
+            }
+            0x4 => self.rtc_address = (self.rtc_address & 0xF0) | usize::from(argument),
+            0x5 => self.rtc_address = (self.rtc_address & 0x0F) | (usize::from(argument) << 4),
+            0x6 => match argument {
+                0x0 => self.latch_clock(),
+                0x1 => self.write_clock(),
+                0x2 => self.rtc_result = 0x01,
+                0xE => {
+                    let tone = self.cartridge.get_clock_memory()[TONE_ADDRESS];
+                    if let Some(ref mut speaker) = self.speaker {
+                        speaker.play_tone(tone);
+                    }
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    // the clock counts minutes of the day and days, it's kept in the
+    // cartridge's rtc data so frontends can save it like the MBC3's
+    fn get_clock(&self) -> (u64, u64) {
+        let (rtc_data, last_time) = self.cartridge.get_last_timestamp();
+        let current_time = self.rtc.get_current_time();
+        let elapsed = current_time.saturating_sub(last_time);
+        let days = u64::from(rtc_data.days_low) | (u64::from(rtc_data.days_high) << 8);
+        let seconds = u64::from(rtc_data.seconds)
+            + u64::from(rtc_data.minutes) * 60
+            + u64::from(rtc_data.hours) * 60 * 60
+            + days * MINUTES_PER_DAY * 60
+            + elapsed;
+        let minutes = seconds / 60;
+        (
+            minutes % MINUTES_PER_DAY,
+            (minutes / MINUTES_PER_DAY) & DAYS_MASK,
+        )
+    }
+
+    fn set_clock(&mut self, minutes: u64, days: u64) {
+        let rtc_data = Rtc {
+            seconds: 0,
+            minutes: (minutes % 60) as u8,
+            hours: (minutes / 60) as u8,
+            days_low: (days & 0xFF) as u8,
+            days_high: ((days >> 8) & 0x0F) as u8,
+        };
+        let current_time = self.rtc.get_current_time();
+        self.cartridge.set_last_timestamp(rtc_data, current_time);
+    }
+
+    fn latch_clock(&mut self) {
+        let (minutes, days) = self.get_clock();
+        let rtc_memory = self.cartridge.get_clock_memory_mut();
+        for i in 0..3 {
+            rtc_memory[TIME_ADDRESS + i] = ((minutes >> (i * 4)) & 0x0F) as u8;
+            rtc_memory[TIME_ADDRESS + 3 + i] = ((days >> (i * 4)) & 0x0F) as u8;
+        }
+    }
+
+    fn write_clock(&mut self) {
+        let mut minutes = 0;
+        let mut days = 0;
+        let rtc_memory = self.cartridge.get_clock_memory();
+        for i in 0..3 {
+            minutes |= u64::from(rtc_memory[TIME_ADDRESS + i]) << (i * 4);
+            days |= u64::from(rtc_memory[TIME_ADDRESS + 3 + i]) << (i * 4);
+        }
+        self.set_clock(minutes % MINUTES_PER_DAY, days & DAYS_MASK);
+    }
+}
@@ -1,4 +1,5 @@
 use super::cartridge::Cartridge;
+use crate::emulator::traits::{Accelerometer, ImageSource, Infrared, Speaker};
 
 pub trait Mbc {
     fn read_byte(&self, index: u16) -> u8;
@@ -6,4 +7,17 @@ pub trait Mbc {
     fn get_cartridge(&self) -> &Cartridge;
     fn get_cartridge_mut(&mut self) -> &mut Cartridge;
     fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>);
+    fn is_rumbling(&self) -> bool {
+        false
+    }
+    fn set_accelerometer(&mut self, _accelerometer: Box<dyn Accelerometer>) {}
+    fn set_infrared(&mut self, _infrared: Box<dyn Infrared>) {}
+    fn set_speaker(&mut self, _speaker: Box<dyn Speaker>) {}
+    fn set_image_source(&mut self, _image_source: Box<dyn ImageSource>) {}
+    // for cartridges with hardware that runs on its own
+    fn step(&mut self, _cycles: i32) {}
+    // the rom bank mapped to 0x4000
+    fn get_rom_bank(&self) -> usize {
+        1
+    }
 }
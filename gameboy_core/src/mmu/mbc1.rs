@@ -1,13 +1,16 @@
 use super::cartridge::Cartridge;
 use super::mbc::Mbc;
+use super::mbc_type::MbcType;
 
 pub struct Mbc1 {
     cartridge: Cartridge,
     selected_rom_bank: usize,
-    selected_eram_bank: usize,
+    higher_bank_bits: usize,
     in_ram_banking_mode: bool,
     external_ram_enabled: bool,
-    higher_rom_bank_bits: usize,
+    // multicarts wire the upper bank bits one bit lower,
+    // so every game gets its own 16 bank block
+    higher_bank_shift: usize,
     ram_change_callback: Box<dyn FnMut(usize, u8)>,
 }
 
@@ -16,21 +19,17 @@ impl Mbc for Mbc1 {
         match index {
             0x0000..=0x3FFF => {
                 let rom = self.cartridge.get_rom();
-                rom[index as usize]
+                let offset = self.get_lower_rom_bank() * 0x4000;
+                rom[index as usize + offset]
             }
             0x4000..=0x7FFF => {
                 let rom = self.cartridge.get_rom();
-                let offset = self.selected_rom_bank * 0x4000;
+                let offset = self.get_upper_rom_bank() * 0x4000;
                 rom[index as usize - 0x4000 + offset]
             }
             0xA000..=0xBFFF => {
                 if self.external_ram_enabled {
-                    let selected_bank = if self.in_ram_banking_mode {
-                        self.selected_eram_bank
-                    } else {
-                        0
-                    };
-                    let offset = selected_bank * 0x2000;
+                    let offset = self.get_eram_bank() * 0x2000;
                     let address = index as usize - 0xA000 + offset;
                     let ram = self.cartridge.get_ram();
                     ram[address]
@@ -50,55 +49,17 @@ impl Mbc for Mbc1 {
                 }
             }
             0x2000..=0x3FFF => {
-                if self.in_ram_banking_mode {
-                    self.selected_rom_bank = usize::from(value & 0x1F);
-                } else {
-                    self.selected_rom_bank =
-                        usize::from(value & 0x1F) | (self.higher_rom_bank_bits << 5);
-                }
-
-                if self.selected_rom_bank == 0x00
-                    || self.selected_rom_bank == 0x20
-                    || self.selected_rom_bank == 0x40
-                    || self.selected_rom_bank == 0x60
-                {
-                    self.selected_rom_bank += 1;
-                }
-
-                self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
-            }
-            0x4000..=0x5FFF => {
-                if self.in_ram_banking_mode {
-                    self.selected_eram_bank = usize::from(value & 0x03);
-                    self.selected_eram_bank &= self.cartridge.get_ram_banks() - 1;
-                } else {
-                    self.higher_rom_bank_bits = usize::from(value & 0x03);
-                    self.selected_rom_bank =
-                        (self.selected_rom_bank & 0x1F) | (self.higher_rom_bank_bits << 5);
-
-                    if self.selected_rom_bank == 0x00
-                        || self.selected_rom_bank == 0x20
-                        || self.selected_rom_bank == 0x40
-                        || self.selected_rom_bank == 0x60
-                    {
-                        self.selected_rom_bank += 1;
-                    }
-                    self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
-                }
-            }
-            0x6000..=0x7FFF => {
-                if !(self.cartridge.get_ram_size() != 3 && (value & 0x01 != 0)) {
-                    self.in_ram_banking_mode = (value & 1) == 1;
+                self.selected_rom_bank = usize::from(value & 0x1F);
+                // the zero check looks at all 5 bits, even on multicarts
+                if self.selected_rom_bank == 0x00 {
+                    self.selected_rom_bank = 0x01;
                 }
             }
+            0x4000..=0x5FFF => self.higher_bank_bits = usize::from(value & 0x03),
+            0x6000..=0x7FFF => self.in_ram_banking_mode = (value & 1) == 1,
             0xA000..=0xBFFF => {
                 if self.external_ram_enabled {
-                    let selected_bank = if self.in_ram_banking_mode {
-                        self.selected_eram_bank
-                    } else {
-                        0
-                    };
-                    let offset = selected_bank * 0x2000;
+                    let offset = self.get_eram_bank() * 0x2000;
                     let address = index as usize - 0xA000 + offset;
                     let ram = self.cartridge.get_ram_mut();
                     ram[address] = value;
@@ -121,18 +82,52 @@ impl Mbc for Mbc1 {
     fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
         self.ram_change_callback = f;
     }
+
+    fn get_rom_bank(&self) -> usize {
+        self.get_upper_rom_bank()
+    }
 }
 
 impl Mbc1 {
     pub fn new(cartridge: Cartridge) -> Mbc1 {
+        let higher_bank_shift = match cartridge.get_mbc_type() {
+            MbcType::Mbc1Multicart => 4,
+            _ => 5,
+        };
         Mbc1 {
             cartridge,
             selected_rom_bank: 1,
-            selected_eram_bank: 0,
+            higher_bank_bits: 0,
             in_ram_banking_mode: false,
             external_ram_enabled: false,
-            higher_rom_bank_bits: 0,
+            higher_bank_shift,
             ram_change_callback: Box::new(|_, _| {}),
         }
     }
+
+    // in ram banking mode the upper bits also switch the first rom bank,
+    // that's how multicarts hand control over to the selected game
+    fn get_lower_rom_bank(&self) -> usize {
+        if self.in_ram_banking_mode {
+            (self.higher_bank_bits << self.higher_bank_shift) & (self.cartridge.get_rom_banks() - 1)
+        } else {
+            0
+        }
+    }
+
+    fn get_upper_rom_bank(&self) -> usize {
+        let lower_bits_mask = (1 << self.higher_bank_shift) - 1;
+        let bank = (self.higher_bank_bits << self.higher_bank_shift)
+            | (self.selected_rom_bank & lower_bits_mask);
+        bank & (self.cartridge.get_rom_banks() - 1)
+    }
+
+    fn get_eram_bank(&self) -> usize {
+        let ram_banks = self.cartridge.get_ram_banks();
+        if self.in_ram_banking_mode && ram_banks > 1 {
+            self.higher_bank_bits & (ram_banks - 1)
+        } else {
+            0
+        }
+    }
 }
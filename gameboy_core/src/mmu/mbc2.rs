@@ -75,6 +75,10 @@ impl Mbc for Mbc2 {
     fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
         self.ram_change_callback = f;
     }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
 }
 
 impl Mbc2 {
@@ -1,5 +1,6 @@
 use super::cartridge::Cartridge;
 use super::mbc::Mbc;
+use super::mbc_type::MbcType;
 use crate::bit_utils;
 use crate::emulator::traits::RTC;
 use crate::rtc::Rtc;
@@ -18,6 +19,8 @@ pub struct Mbc3 {
     rtc_latch_data: u8,
     rtc_latch: Rtc,
     rtc_data: Rtc,
+    rom_bank_mask: u8,
+    max_eram_bank: u8,
 }
 
 impl Mbc for Mbc3 {
@@ -58,7 +61,7 @@ impl Mbc for Mbc3 {
         match index {
             0x0000..=0x1FFF => self.external_ram_enabled = (value & 0x0F) == 0x0A,
             0x2000..=0x3FFF => {
-                self.selected_rom_bank = usize::from(value & 0x7F);
+                self.selected_rom_bank = usize::from(value & self.rom_bank_mask);
                 if self.selected_rom_bank == 0 {
                     self.selected_rom_bank = 1;
                 }
@@ -66,7 +69,7 @@ impl Mbc for Mbc3 {
             }
             0x4000..=0x5FFF => {
                 match value {
-                    0x00..=0x03 => {
+                    0x00..=0x07 if value <= self.max_eram_bank => {
                         self.selected_eram_bank = value as usize;
                         self.selected_eram_bank &= self.cartridge.get_ram_banks() - 1;
                         self.use_rtc_for_ram = false;
@@ -129,6 +132,10 @@ impl Mbc for Mbc3 {
     fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
         self.ram_change_callback = f;
     }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
 }
 
 impl Mbc3 {
@@ -139,6 +146,12 @@ impl Mbc3 {
             rtc_last_time = rtc.get_current_time();
         }
 
+        // the MBC30 uses all 8 bits for the rom bank and has 8 ram banks
+        let (rom_bank_mask, max_eram_bank) = match cartridge.get_mbc_type() {
+            MbcType::Mbc30 => (0xFF, 0x07),
+            _ => (0x7F, 0x03),
+        };
+
         let mut mbc3 = Mbc3 {
             cartridge,
             selected_rom_bank: 1,
@@ -153,6 +166,8 @@ impl Mbc3 {
             rtc_latch_data: 0,
             rtc_data,
             rtc_latch: rtc_data,
+            rom_bank_mask,
+            max_eram_bank,
         };
         mbc3.update_rtc_latch();
 
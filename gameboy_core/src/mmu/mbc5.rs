@@ -7,6 +7,7 @@ pub struct Mbc5 {
     selected_rom_bank_high: usize,
     selected_eram_bank: usize,
     external_ram_enabled: bool,
+    rumbling: bool,
     ram_change_callback: Box<dyn FnMut(usize, u8)>,
 }
 
@@ -49,7 +50,13 @@ impl Mbc for Mbc5 {
                 self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1
             }
             0x4000..=0x5FFF => {
-                self.selected_eram_bank = usize::from(value) & 0x0F;
+                // on rumble carts bit 3 drives the motor instead of selecting a bank
+                if self.cartridge.has_rumble() {
+                    self.rumbling = (value & 0x08) != 0;
+                    self.selected_eram_bank = usize::from(value) & 0x07;
+                } else {
+                    self.selected_eram_bank = usize::from(value) & 0x0F;
+                }
                 self.selected_eram_bank &= self.cartridge.get_ram_banks() - 1;
             }
             0x6000..=0x7FFF => (),
@@ -78,6 +85,14 @@ impl Mbc for Mbc5 {
     fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
         self.ram_change_callback = f;
     }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+
+    fn is_rumbling(&self) -> bool {
+        self.rumbling
+    }
 }
 
 impl Mbc5 {
@@ -88,6 +103,7 @@ impl Mbc5 {
             selected_rom_bank_high: 0,
             selected_eram_bank: 0,
             external_ram_enabled: false,
+            rumbling: false,
             ram_change_callback: Box::new(|_, _| {}),
         }
     }
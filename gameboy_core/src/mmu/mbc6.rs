@@ -0,0 +1,158 @@
+use super::cartridge::Cartridge;
+use super::flash::Flash;
+use super::mbc::Mbc;
+use crate::bit_utils;
+
+// the flash is kept after the ram in the cartridge's ram, so it's saved with it
+const RAM_SIZE: usize = 0x8000;
+const FLASH_SIZE: usize = 0x100000;
+
+// both halves of the rom area and the ram area are split into two windows,
+// A and B, that are switched on their own
+#[derive(Clone, Copy)]
+struct RomWindow {
+    bank: usize,
+    maps_flash: bool,
+}
+
+pub struct Mbc6 {
+    cartridge: Cartridge,
+    rom_windows: [RomWindow; 2],
+    ram_banks: [usize; 2],
+    external_ram_enabled: bool,
+    flash_enabled: bool,
+    flash_write_enabled: bool,
+    flash: Flash,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Mbc6 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let (maps_flash, offset) = self.get_rom_window_offset(index);
+                if maps_flash {
+                    if self.flash_enabled {
+                        let flash = &self.cartridge.get_ram()[RAM_SIZE..];
+                        self.flash.read_byte(offset, flash)
+                    } else {
+                        0xFF
+                    }
+                } else {
+                    let rom = self.cartridge.get_rom();
+                    rom[offset]
+                }
+            }
+            0xA000..=0xBFFF => {
+                if self.external_ram_enabled {
+                    let ram = self.cartridge.get_ram();
+                    ram[self.get_ram_address(index)]
+                } else {
+                    0xFF
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x03FF => self.external_ram_enabled = (value & 0x0F) == 0x0A,
+            0x0400..=0x07FF => self.ram_banks[0] = usize::from(value & 0x07),
+            0x0800..=0x0BFF => self.ram_banks[1] = usize::from(value & 0x07),
+            0x0C00..=0x0FFF => self.flash_enabled = bit_utils::is_set(value, 0),
+            0x1000 => self.flash_write_enabled = bit_utils::is_set(value, 0),
+            0x1001..=0x1FFF => (),
+            0x2000..=0x27FF => self.rom_windows[0].bank = usize::from(value & 0x7F),
+            0x2800..=0x2FFF => self.rom_windows[0].maps_flash = value == 0x08,
+            0x3000..=0x37FF => self.rom_windows[1].bank = usize::from(value & 0x7F),
+            0x3800..=0x3FFF => self.rom_windows[1].maps_flash = value == 0x08,
+            0x4000..=0x7FFF => {
+                let (maps_flash, offset) = self.get_rom_window_offset(index);
+                if maps_flash && self.flash_enabled {
+                    let flash = &mut self.cartridge.get_ram_mut()[RAM_SIZE..];
+                    let changed =
+                        self.flash
+                            .write_byte(offset, value, flash, self.flash_write_enabled);
+                    for address in changed {
+                        let address = RAM_SIZE + address;
+                        let value = self.cartridge.get_ram()[address];
+                        (self.ram_change_callback)(address, value);
+                    }
+                }
+            }
+            0xA000..=0xBFFF => {
+                if self.external_ram_enabled {
+                    let address = self.get_ram_address(index);
+                    let ram = self.cartridge.get_ram_mut();
+                    ram[address] = value;
+
+                    (self.ram_change_callback)(address, value);
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+}
+
+impl Mbc6 {
+    pub fn new(cartridge: Cartridge) -> Mbc6 {
+        Mbc6 {
+            cartridge,
+            rom_windows: [
+                RomWindow {
+                    bank: 2,
+                    maps_flash: false,
+                },
+                RomWindow {
+                    bank: 3,
+                    maps_flash: false,
+                },
+            ],
+            ram_banks: [0, 1],
+            external_ram_enabled: false,
+            flash_enabled: false,
+            flash_write_enabled: false,
+            flash: Flash::new(),
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    // the banks are 8KB, half the size of the usual rom banks,
+    // returns whether the window maps the flash and the offset into it
+    fn get_rom_window_offset(&self, index: u16) -> (bool, usize) {
+        let window = self.rom_windows[usize::from((index - 0x4000) >> 13)];
+        let banks = if window.maps_flash {
+            FLASH_SIZE / 0x2000
+        } else {
+            self.cartridge.get_rom_banks() * 2
+        };
+        let bank = window.bank & (banks - 1);
+        (
+            window.maps_flash,
+            bank * 0x2000 + usize::from(index & 0x1FFF),
+        )
+    }
+
+    // the ram banks are 4KB, so every window has its own half of the ram area
+    fn get_ram_address(&self, index: u16) -> usize {
+        let bank = self.ram_banks[usize::from((index - 0xA000) >> 12)];
+        bank * 0x1000 + usize::from(index & 0x0FFF)
+    }
+}
@@ -0,0 +1,144 @@
+use super::cartridge::Cartridge;
+use super::eeprom::Eeprom;
+use super::mbc::Mbc;
+use crate::emulator::traits::Accelerometer;
+
+// the accelerometer reads 0x81D0 when level, and moves about 0x70 per g
+const ACCELEROMETER_CENTER: f32 = 33232.0;
+const ACCELEROMETER_GRAVITY: f32 = 112.0;
+const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+pub struct Mbc7 {
+    cartridge: Cartridge,
+    selected_rom_bank: usize,
+    ram_enabled_1: bool,
+    ram_enabled_2: bool,
+    accelerometer: Option<Box<dyn Accelerometer>>,
+    accelerometer_x: u16,
+    accelerometer_y: u16,
+    accelerometer_erased: bool,
+    eeprom: Eeprom,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Mbc7 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.selected_rom_bank * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xAFFF => {
+                if self.ram_enabled_1 && self.ram_enabled_2 {
+                    match (index >> 4) & 0x0F {
+                        0x2 => self.accelerometer_x as u8,
+                        0x3 => (self.accelerometer_x >> 8) as u8,
+                        0x4 => self.accelerometer_y as u8,
+                        0x5 => (self.accelerometer_y >> 8) as u8,
+                        0x6 => 0x00,
+                        0x8 => self.eeprom.read_byte(),
+                        _ => 0xFF,
+                    }
+                } else {
+                    0xFF
+                }
+            }
+            0xB000..=0xBFFF => 0xFF,
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x1FFF => {
+                self.ram_enabled_1 = value == 0x0A;
+                if !self.ram_enabled_1 {
+                    self.ram_enabled_2 = false;
+                }
+            }
+            0x2000..=0x3FFF => {
+                self.selected_rom_bank = usize::from(value & 0x7F);
+                self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
+            }
+            0x4000..=0x5FFF if self.ram_enabled_1 => self.ram_enabled_2 = value == 0x40,
+            0x4000..=0x7FFF => (),
+            0xA000..=0xAFFF => {
+                if self.ram_enabled_1 && self.ram_enabled_2 {
+                    match (index >> 4) & 0x0F {
+                        0x0 if value == 0x55 => {
+                            self.accelerometer_x = ACCELEROMETER_ERASED;
+                            self.accelerometer_y = ACCELEROMETER_ERASED;
+                            self.accelerometer_erased = true;
+                        }
+                        0x1 if value == 0xAA && self.accelerometer_erased => {
+                            self.latch_accelerometer();
+                            self.accelerometer_erased = false;
+                        }
+                        0x8 => {
+                            let changed =
+                                self.eeprom.write_byte(value, self.cartridge.get_ram_mut());
+                            for address in changed {
+                                let value = self.cartridge.get_ram()[address];
+                                (self.ram_change_callback)(address, value);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            0xB000..=0xBFFF => (),
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+
+    fn set_accelerometer(&mut self, accelerometer: Box<dyn Accelerometer>) {
+        self.accelerometer = Some(accelerometer);
+    }
+}
+
+impl Mbc7 {
+    pub fn new(cartridge: Cartridge) -> Mbc7 {
+        Mbc7 {
+            cartridge,
+            selected_rom_bank: 1,
+            ram_enabled_1: false,
+            ram_enabled_2: false,
+            accelerometer: None,
+            accelerometer_x: ACCELEROMETER_ERASED,
+            accelerometer_y: ACCELEROMETER_ERASED,
+            accelerometer_erased: false,
+            eeprom: Eeprom::new(),
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    fn latch_accelerometer(&mut self) {
+        let (x, y) = match self.accelerometer {
+            Some(ref accelerometer) => accelerometer.get_acceleration(),
+            None => (0.0, 0.0),
+        };
+        self.accelerometer_x = (ACCELEROMETER_CENTER - x * ACCELEROMETER_GRAVITY) as u16;
+        self.accelerometer_y = (ACCELEROMETER_CENTER - y * ACCELEROMETER_GRAVITY) as u16;
+    }
+}
@@ -2,7 +2,16 @@
 pub enum MbcType {
     RomOnly,
     Mbc1,
+    Mbc1Multicart,
     Mbc2,
     Mbc3,
+    Mbc30,
     Mbc5,
+    Mbc6,
+    Mbc7,
+    Mmm01,
+    Huc1,
+    Huc3,
+    PocketCamera,
+    Tama5,
 }
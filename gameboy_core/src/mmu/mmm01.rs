@@ -0,0 +1,171 @@
+use super::cartridge::Cartridge;
+use super::mbc::Mbc;
+use crate::bit_utils;
+
+// the menu sits in the last 32KB of the rom, which is mapped until it picks a game.
+// it then sets up the game's banks, and which bank bits the game is allowed to change,
+// and locks the mapping, after which it works like an MBC1 within the game's banks
+pub struct Mmm01 {
+    cartridge: Cartridge,
+    is_mapped: bool,
+    rom_bank_low: usize,
+    rom_bank_mid: usize,
+    rom_bank_high: usize,
+    // the bank bits that are set when locking and can't be changed by the game anymore
+    rom_bank_mask: usize,
+    ram_bank_low: usize,
+    ram_bank_high: usize,
+    ram_bank_mask: usize,
+    in_ram_banking_mode: bool,
+    ram_banking_mode_locked: bool,
+    external_ram_enabled: bool,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Mmm01 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.get_lower_rom_bank() * 0x4000;
+                rom[index as usize + offset]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.get_upper_rom_bank() * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xBFFF => {
+                if self.external_ram_enabled && self.cartridge.get_ram_size() > 0 {
+                    let ram = self.cartridge.get_ram();
+                    let offset = self.get_eram_bank() * 0x2000;
+                    ram[index as usize - 0xA000 + offset]
+                } else {
+                    0xFF
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        let value = usize::from(value);
+        match index {
+            0x0000..=0x1FFF => {
+                self.external_ram_enabled = (value & 0x0F) == 0x0A;
+                if !self.is_mapped {
+                    self.ram_bank_mask = (value >> 4) & 0x03;
+                    self.is_mapped = bit_utils::is_set(value as u8, 6);
+                }
+            }
+            0x2000..=0x3FFF => {
+                let mask = self.rom_bank_mask << 1;
+                let mut bank = (self.rom_bank_low & mask) | (value & 0x1F & !mask);
+                // like on the MBC1, bank 0 can't be selected, but only the bits
+                // the game can change are checked
+                if bank & !mask == 0x00 {
+                    bank |= 0x01;
+                }
+                self.rom_bank_low = bank;
+                if !self.is_mapped {
+                    self.rom_bank_mid = (value >> 5) & 0x03;
+                }
+            }
+            0x4000..=0x5FFF => {
+                let mask = self.ram_bank_mask;
+                self.ram_bank_low = (self.ram_bank_low & mask) | (value & 0x03 & !mask);
+                if !self.is_mapped {
+                    self.ram_bank_high = (value >> 2) & 0x03;
+                    self.rom_bank_high = (value >> 4) & 0x03;
+                    self.ram_banking_mode_locked = bit_utils::is_set(value as u8, 6);
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !self.ram_banking_mode_locked {
+                    self.in_ram_banking_mode = (value & 1) == 1;
+                }
+                if !self.is_mapped {
+                    // bit 6 would multiplex the upper bank bits, no released game uses it
+                    self.rom_bank_mask = (value >> 2) & 0x0F;
+                }
+            }
+            0xA000..=0xBFFF => {
+                if self.external_ram_enabled && self.cartridge.get_ram_size() > 0 {
+                    let offset = self.get_eram_bank() * 0x2000;
+                    let ram = self.cartridge.get_ram_mut();
+                    let address = index as usize - 0xA000 + offset;
+                    ram[address] = value as u8;
+
+                    (self.ram_change_callback)(address, value as u8);
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.get_upper_rom_bank()
+    }
+}
+
+impl Mmm01 {
+    pub fn new(cartridge: Cartridge) -> Mmm01 {
+        Mmm01 {
+            cartridge,
+            is_mapped: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            in_ram_banking_mode: false,
+            ram_banking_mode_locked: false,
+            external_ram_enabled: false,
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    // the game's first bank keeps the bank bits that were locked by the menu
+    fn get_lower_rom_bank(&self) -> usize {
+        let bank = if self.is_mapped {
+            let mask = self.rom_bank_mask << 1;
+            (self.rom_bank_high << 7) | (self.rom_bank_mid << 5) | (self.rom_bank_low & mask)
+        } else {
+            0x1FE
+        };
+        bank & (self.cartridge.get_rom_banks() - 1)
+    }
+
+    fn get_upper_rom_bank(&self) -> usize {
+        let bank = if self.is_mapped {
+            (self.rom_bank_high << 7) | (self.rom_bank_mid << 5) | self.rom_bank_low
+        } else {
+            0x1FF
+        };
+        bank & (self.cartridge.get_rom_banks() - 1)
+    }
+
+    fn get_eram_bank(&self) -> usize {
+        let ram_banks = std::cmp::max(self.cartridge.get_ram_banks(), 1);
+        let low = if self.in_ram_banking_mode {
+            self.ram_bank_low
+        } else {
+            self.ram_bank_low & self.ram_bank_mask
+        };
+        ((self.ram_bank_high << 2) | low) & (ram_banks - 1)
+    }
+}
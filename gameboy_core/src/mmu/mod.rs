@@ -1,28 +1,48 @@
 pub mod cartridge;
+mod eeprom;
+mod flash;
 pub mod gpu_cycles;
+mod huc1;
+mod huc3;
 pub mod interrupt;
 mod mbc;
 mod mbc1;
 mod mbc2;
 mod mbc3;
 mod mbc5;
+mod mbc6;
+mod mbc7;
 mod mbc_type;
+mod mmm01;
+pub mod pocket_camera;
 mod rom_only;
+mod tama5;
 use self::cartridge::Cartridge;
 use self::gpu_cycles::GpuCycles;
+use self::huc1::Huc1;
+use self::huc3::Huc3;
 use self::interrupt::Interrupt;
 use self::mbc::Mbc;
 use self::mbc1::Mbc1;
 use self::mbc2::Mbc2;
 use self::mbc3::Mbc3;
 use self::mbc5::Mbc5;
+use self::mbc6::Mbc6;
+use self::mbc7::Mbc7;
 use self::mbc_type::MbcType;
+use self::mmm01::Mmm01;
+use self::pocket_camera::PocketCamera;
 use self::rom_only::RomOnly;
+use self::tama5::Tama5;
 use crate::bit_utils;
-use crate::emulator::traits::RTC;
+use crate::cheats::{Cheat, CheatKind};
+use crate::debugger::{WatchKind, Watchpoint};
+use crate::emulator::traits::{Accelerometer, ImageSource, Infrared, Speaker, RTC};
 use crate::gpu::cgb_color::CGBColor;
 use crate::gpu::lcd_control_flag::LcdControlFlag;
+use crate::ram_search::RamSnapshot;
 use crate::sound::Sound;
+use std::cell::Cell;
 
 pub const SPRITES_START_INDEX: u16 = 0xFE00;
 pub const JOYPAD_INDEX: u16 = 0xFF00;
@@ -113,6 +133,17 @@ pub struct Memory {
     pub cgb_background_palettes: [[CGBColor; 4]; 8],
     pub cgb_sprite_palettes: [[CGBColor; 4]; 8],
     sound: Sound,
+    // the sound is stepped after each instruction, but its registers are
+    // accessed part way through one, this is how far into it the access is
+    // and how many cycles the sound is behind the start of the instruction
+    sound_access_delay: i32,
+    sound_lag: i32,
+    sound_buffer_full: bool,
+    cheats: Vec<Cheat>,
+    watchpoints: Vec<Watchpoint>,
+    // watchpoints only look at the cpu's accesses, not the gpu's
+    watchpoints_armed: bool,
+    watchpoint_hit: Cell<Option<(Watchpoint, Option<u8>)>>,
 }
 
 impl Memory {
@@ -127,10 +158,17 @@ impl Memory {
 
         let mbc: Box<dyn Mbc> = match cartridge.get_mbc_type() {
             MbcType::RomOnly => Box::new(RomOnly::new(cartridge)),
-            MbcType::Mbc1 => Box::new(Mbc1::new(cartridge)),
+            MbcType::Mbc1 | MbcType::Mbc1Multicart => Box::new(Mbc1::new(cartridge)),
             MbcType::Mbc2 => Box::new(Mbc2::new(cartridge)),
-            MbcType::Mbc3 => Box::new(Mbc3::new(cartridge, rtc)),
+            MbcType::Mbc3 | MbcType::Mbc30 => Box::new(Mbc3::new(cartridge, rtc)),
             MbcType::Mbc5 => Box::new(Mbc5::new(cartridge)),
+            MbcType::Mbc6 => Box::new(Mbc6::new(cartridge)),
+            MbcType::Mbc7 => Box::new(Mbc7::new(cartridge)),
+            MbcType::Mmm01 => Box::new(Mmm01::new(cartridge)),
+            MbcType::Huc1 => Box::new(Huc1::new(cartridge)),
+            MbcType::Huc3 => Box::new(Huc3::new(cartridge, rtc)),
+            MbcType::PocketCamera => Box::new(PocketCamera::new(cartridge)),
+            MbcType::Tama5 => Box::new(Tama5::new(cartridge, rtc)),
         };
 
         let vram = if is_cgb {
@@ -163,7 +201,7 @@ impl Memory {
         }
 
         // setup initial values for the sound module
-        let mut sound = Sound::new();
+        let mut sound = Sound::new(is_cgb);
         for i in 0xFF10..=0xFF3F {
             let value = if is_cgb {
                 INITIAL_VALUES_FOR_COLOR_FFXX[i - 0xFF00]
@@ -203,12 +241,29 @@ impl Memory {
             cgb_background_palettes: [[white; 4]; 8],
             cgb_sprite_palettes: [[white; 4]; 8],
             sound,
+            sound_access_delay: 0,
+            sound_lag: 0,
+            sound_buffer_full: false,
+            cheats: Vec::new(),
+            watchpoints: Vec::new(),
+            watchpoints_armed: false,
+            watchpoint_hit: Cell::new(None),
         }
     }
 
     pub fn read_byte(&self, index: u16) -> u8 {
+        if self.watchpoints_armed {
+            self.check_watchpoints(index, None);
+        }
         match index {
-            0x0000..=0x7FFF => self.mbc.read_byte(index),
+            0x0000..=0x7FFF => {
+                let value = self.mbc.read_byte(index);
+                if self.cheats.is_empty() {
+                    value
+                } else {
+                    self.apply_game_genie_codes(index, value)
+                }
+            }
             0x8000..=0x9FFF => self.read_cgb_lcd_ram(index, self.vram_bank),
             0xA000..=0xBFFF => self.mbc.read_byte(index),
             0xC000..=0xCFFF => self.read_cgb_wram(index - 0xC000, 0),
@@ -224,7 +279,9 @@ impl Memory {
                 TIMER_CONTROL_INDEX => self.load(index) | 0xF8,
                 0xFF0E => 0xFF,
                 INTERRUPT_FLAGS_INDEX => self.load(index) | 0xE0,
-                APU_INDEX_START..=APU_INDEX_END => self.sound.read_byte(index),
+                APU_INDEX_START..=APU_INDEX_END => self
+                    .sound
+                    .read_byte_ahead(index, self.sound_lag + self.sound_access_delay),
                 LCD_CONTROL_INDEX => self.load(index),
                 LCD_INDEX => self.load(index) | 0x80,
                 SCROLL_Y_INDEX => self.load(index),
@@ -311,6 +368,9 @@ impl Memory {
     }
 
     pub fn write_byte(&mut self, index: u16, value: u8) {
+        if self.watchpoints_armed {
+            self.check_watchpoints(index, Some(value));
+        }
         match index {
             0x0000..=0x7FFF => self.mbc.write_byte(index, value),
             0x8000..=0x9FFF => self.write_cgb_lcd_ram(index, value, self.vram_bank),
@@ -325,7 +385,10 @@ impl Memory {
                 TIMER_RESET_INDEX => self.store(index, value),
                 TIMER_CONTROL_INDEX => self.store(index, value),
                 INTERRUPT_FLAGS_INDEX => self.store(index, value & 0x1F),
-                APU_INDEX_START..=APU_INDEX_END => self.sound.write_byte(index, value),
+                APU_INDEX_START..=APU_INDEX_END => {
+                    self.catch_up_sound();
+                    self.sound.write_byte(index, value);
+                }
                 LCD_CONTROL_INDEX => self.do_lcd_control_write(value),
                 LCD_INDEX => self.do_lcd_status_write(value),
                 SCROLL_Y_INDEX => self.store(index, value),
@@ -697,6 +760,10 @@ impl Memory {
         }
     }
 
+    pub fn is_cgb(&self) -> bool {
+        self.is_cgb
+    }
+
     pub fn read_cgb_lcd_ram(&self, index: u16, bank: i32) -> u8 {
         let offset = 0x2000 * bank as usize;
         let address = index as usize - 0x8000 + offset;
@@ -721,6 +788,15 @@ impl Memory {
         self.wram[address] = value;
     }
 
+    // goes around the mbc, so the bank doesn't have to be mapped in,
+    // banks the cartridge doesn't have are ignored
+    fn write_cartridge_ram(&mut self, index: u16, value: u8, bank: usize) {
+        let address = index as usize + 0x2000 * bank;
+        if let Some(byte) = self.mbc.get_cartridge_mut().get_ram_mut().get_mut(address) {
+            *byte = value;
+        }
+    }
+
     pub fn read_word(&self, index: u16) -> u16 {
         let low = u16::from(self.read_byte(index));
         let high = u16::from(self.read_byte(index + 1));
@@ -794,10 +870,168 @@ impl Memory {
         self.mbc.set_ram_change_callback(f);
     }
 
+    pub fn is_rumbling(&self) -> bool {
+        self.mbc.is_rumbling()
+    }
+
+    pub fn set_accelerometer(&mut self, accelerometer: Box<dyn Accelerometer>) {
+        self.mbc.set_accelerometer(accelerometer);
+    }
+
+    pub fn set_infrared(&mut self, infrared: Box<dyn Infrared>) {
+        self.mbc.set_infrared(infrared);
+    }
+
+    pub fn set_speaker(&mut self, speaker: Box<dyn Speaker>) {
+        self.mbc.set_speaker(speaker);
+    }
+
+    pub fn set_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        self.mbc.set_image_source(image_source);
+    }
+
+    pub fn step_mbc(&mut self, cycles: i32) {
+        self.mbc.step(cycles);
+    }
+
     pub fn get_cartridge_mut(&mut self) -> &mut Cartridge {
         self.mbc.get_cartridge_mut()
     }
 
+    pub fn get_rom_bank(&self) -> usize {
+        self.mbc.get_rom_bank()
+    }
+
+    pub fn get_watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn get_watchpoints_mut(&mut self) -> &mut Vec<Watchpoint> {
+        &mut self.watchpoints
+    }
+
+    pub fn set_watchpoints_armed(&mut self, armed: bool) {
+        self.watchpoints_armed = armed && !self.watchpoints.is_empty();
+    }
+
+    // the first hit since the last call, with the value for writes
+    pub fn take_watchpoint_hit(&mut self) -> Option<(Watchpoint, Option<u8>)> {
+        self.watchpoint_hit.take()
+    }
+
+    fn check_watchpoints(&self, index: u16, value: Option<u8>) {
+        if self.watchpoint_hit.get().is_some() {
+            return;
+        }
+        let is_write = value.is_some();
+        let hit = self.watchpoints.iter().find(|watchpoint| {
+            watchpoint.address == index
+                && match watchpoint.kind {
+                    WatchKind::Read => !is_write,
+                    WatchKind::Write => is_write,
+                    WatchKind::ReadWrite => true,
+                }
+        });
+        if let Some(&watchpoint) = hit {
+            self.watchpoint_hit.set(Some((watchpoint, value)));
+        }
+    }
+
+    pub fn get_cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn get_cheats_mut(&mut self) -> &mut Vec<Cheat> {
+        &mut self.cheats
+    }
+
+    fn apply_game_genie_codes(&self, index: u16, value: u8) -> u8 {
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            if let CheatKind::GameGenie {
+                address,
+                value: new_value,
+                compare,
+            } = cheat.kind
+            {
+                if address == index && compare.unwrap_or(value) == value {
+                    return new_value;
+                }
+            }
+        }
+        value
+    }
+
+    pub fn get_ram_snapshot(&self) -> RamSnapshot {
+        let cartridge = self.mbc.get_cartridge();
+        let cartridge_ram_size = match cartridge.get_mbc_type() {
+            MbcType::Mbc2 => 0x200,
+            _ => cartridge.get_ram_banks() * 0x2000,
+        };
+        let cartridge_ram = cartridge.get_ram();
+        let cartridge_ram_size = std::cmp::min(cartridge_ram_size, cartridge_ram.len());
+        RamSnapshot {
+            work_ram: self.wram.clone(),
+            high_ram: self.high_ram[0x80..0xFF].to_vec(),
+            cartridge_ram: cartridge_ram[..cartridge_ram_size].to_vec(),
+        }
+    }
+
+    pub fn apply_game_shark_codes(&mut self) {
+        let codes: Vec<CheatKind> = self
+            .cheats
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .map(|cheat| cheat.kind)
+            .collect();
+        for kind in codes {
+            if let CheatKind::GameShark {
+                bank,
+                address,
+                value,
+            } = kind
+            {
+                match address {
+                    0xD000..=0xDFFF if self.is_cgb && bank & 0xF0 == 0x90 => {
+                        let bank = std::cmp::max(i32::from(bank & 0x07), 1);
+                        self.write_cgb_wram(address - 0xD000, value, bank);
+                    }
+                    0xA000..=0xBFFF if bank & 0xF0 == 0x00 => {
+                        self.write_cartridge_ram(address - 0xA000, value, bank as usize);
+                    }
+                    _ => self.write_byte(address, value),
+                }
+            }
+        }
+    }
+
+    pub fn set_sound_access_delay(&mut self, cycles: i32) {
+        self.sound_access_delay = cycles;
+    }
+
+    fn catch_up_sound(&mut self) {
+        let cycles = self.sound_lag + self.sound_access_delay;
+        if cycles > 0 {
+            if self.sound.step(cycles) {
+                self.sound_buffer_full = true;
+            }
+            self.sound_lag -= cycles;
+        }
+    }
+
+    // runs the sound to the end of the instruction, returns true when the
+    // audio buffer is full. a buffer filled part way through the instruction
+    // has to be read first, so the rest of the cycles are run after that
+    pub fn step_sound(&mut self, cycles: i32) -> bool {
+        self.sound_lag += cycles;
+        self.sound_access_delay = 0;
+        if self.sound_buffer_full {
+            self.sound_buffer_full = false;
+            return true;
+        }
+        let cycles = std::mem::replace(&mut self.sound_lag, 0);
+        cycles > 0 && self.sound.step(cycles)
+    }
+
     pub fn get_sound(&self) -> &Sound {
         &self.sound
     }
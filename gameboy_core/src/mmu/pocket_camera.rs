@@ -0,0 +1,260 @@
+use super::cartridge::Cartridge;
+use super::mbc::Mbc;
+use crate::bit_utils;
+use crate::emulator::traits::ImageSource;
+
+pub const CAMERA_WIDTH: usize = 128;
+pub const CAMERA_HEIGHT: usize = 112;
+const REGISTER_COUNT: usize = 0x36;
+const IMAGE_RAM_START: usize = 0x0100;
+const DITHER_MATRIX_START: usize = 0x06;
+const EDGE_ENHANCEMENT_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+pub struct PocketCamera {
+    cartridge: Cartridge,
+    selected_rom_bank: usize,
+    selected_eram_bank: usize,
+    external_ram_enabled: bool,
+    // bank 0x10 maps the M64282FP sensor's registers instead of ram
+    registers_mapped: bool,
+    registers: [u8; REGISTER_COUNT],
+    capture_cycles: i32,
+    image_source: Option<Box<dyn ImageSource>>,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for PocketCamera {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.selected_rom_bank * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xBFFF => {
+                if self.registers_mapped {
+                    // only the control register can be read back
+                    if (index - 0xA000) & 0x7F == 0 {
+                        self.registers[0] & 0x07
+                    } else {
+                        0x00
+                    }
+                } else {
+                    let ram = self.cartridge.get_ram();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    ram[index as usize - 0xA000 + offset]
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x1FFF => self.external_ram_enabled = (value & 0x0F) == 0x0A,
+            0x2000..=0x3FFF => {
+                self.selected_rom_bank = usize::from(value & 0x3F);
+                self.selected_rom_bank &= self.cartridge.get_rom_banks() - 1;
+            }
+            0x4000..=0x5FFF => {
+                self.registers_mapped = bit_utils::is_set(value, 4);
+                self.selected_eram_bank = usize::from(value & 0x0F);
+            }
+            0x6000..=0x7FFF => (),
+            0xA000..=0xBFFF => {
+                if self.registers_mapped {
+                    let register = usize::from((index - 0xA000) & 0x7F);
+                    if register == 0 {
+                        self.write_control(value);
+                    } else if register < REGISTER_COUNT {
+                        self.registers[register] = value;
+                    }
+                } else if self.external_ram_enabled && !self.is_capturing() {
+                    let ram = self.cartridge.get_ram_mut();
+                    let offset = self.selected_eram_bank * 0x2000;
+                    let address = index as usize - 0xA000 + offset;
+                    ram[address] = value;
+
+                    (self.ram_change_callback)(address, value);
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+
+    fn set_image_source(&mut self, image_source: Box<dyn ImageSource>) {
+        self.image_source = Some(image_source);
+    }
+
+    fn step(&mut self, cycles: i32) {
+        if self.is_capturing() {
+            self.capture_cycles -= cycles;
+            if self.capture_cycles <= 0 {
+                self.capture_cycles = 0;
+                self.registers[0] &= !0x01;
+                self.capture();
+            }
+        }
+    }
+}
+
+impl PocketCamera {
+    pub fn new(cartridge: Cartridge) -> PocketCamera {
+        PocketCamera {
+            cartridge,
+            selected_rom_bank: 1,
+            selected_eram_bank: 0,
+            external_ram_enabled: false,
+            registers_mapped: false,
+            registers: [0; REGISTER_COUNT],
+            capture_cycles: 0,
+            image_source: None,
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    fn is_capturing(&self) -> bool {
+        bit_utils::is_set(self.registers[0], 0)
+    }
+
+    fn write_control(&mut self, value: u8) {
+        let was_capturing = self.is_capturing();
+        self.registers[0] = value & 0x07;
+        if !was_capturing && self.is_capturing() {
+            self.capture_cycles = self.get_capture_cycles();
+        } else if was_capturing {
+            // a capture can't be stopped once it has started
+            self.registers[0] |= 0x01;
+        }
+    }
+
+    // the sensor needs a fixed amount of time to read out the image,
+    // plus the exposure time, which is counted in steps of 16 microseconds
+    fn get_capture_cycles(&self) -> i32 {
+        let n_bit = if bit_utils::is_set(self.registers[1], 7) {
+            0
+        } else {
+            512
+        };
+        (32446 + n_bit + 16 * i32::from(self.get_exposure())) * 4
+    }
+
+    fn get_exposure(&self) -> u16 {
+        (u16::from(self.registers[2]) << 8) | u16::from(self.registers[3])
+    }
+
+    fn capture(&mut self) {
+        let mut pixels = vec![0x80; CAMERA_WIDTH * CAMERA_HEIGHT];
+        match self.image_source {
+            Some(ref mut image_source) => image_source.capture(&mut pixels),
+            None => test_pattern(&mut pixels),
+        }
+
+        let processed = self.process_image(&pixels);
+        let ram = self.cartridge.get_ram_mut();
+        for tile_y in 0..CAMERA_HEIGHT / 8 {
+            for tile_x in 0..CAMERA_WIDTH / 8 {
+                for row in 0..8 {
+                    let mut low = 0;
+                    let mut high = 0;
+                    for column in 0..8 {
+                        let x = tile_x * 8 + column;
+                        let y = tile_y * 8 + row;
+                        let shade = processed[y * CAMERA_WIDTH + x];
+                        low = (low << 1) | (shade & 0x01);
+                        high = (high << 1) | ((shade >> 1) & 0x01);
+                    }
+                    let tile = tile_y * (CAMERA_WIDTH / 8) + tile_x;
+                    let address = IMAGE_RAM_START + tile * 16 + row * 2;
+                    ram[address] = low;
+                    ram[address + 1] = high;
+                }
+            }
+        }
+
+        for address in IMAGE_RAM_START..IMAGE_RAM_START + CAMERA_WIDTH * CAMERA_HEIGHT / 4 {
+            let value = self.cartridge.get_ram()[address];
+            (self.ram_change_callback)(address, value);
+        }
+    }
+
+    // turns the sensor's brightness values into gameboy shades,
+    // 0 is white and 3 is black
+    fn process_image(&self, pixels: &[u8]) -> Vec<u8> {
+        let invert = bit_utils::is_set(self.registers[4], 3);
+        // the gain goes up in steps of about 1.5dB from the lowest setting
+        let gain = self.registers[1] & 0x1F;
+        let gain = 10f32.powf(f32::from(gain) * 1.5 / 20.0);
+        let exposure = f32::from(self.get_exposure()) / 0x1000 as f32;
+        let sensor_value = |x: isize, y: isize| -> f32 {
+            let x = x.clamp(0, CAMERA_WIDTH as isize - 1) as usize;
+            let y = y.clamp(0, CAMERA_HEIGHT as isize - 1) as usize;
+            let mut value = f32::from(pixels[y * CAMERA_WIDTH + x]);
+            if invert {
+                value = 255.0 - value;
+            }
+            value * gain * exposure
+        };
+
+        let edge_enhancement = (self.registers[1] & 0xE0) == 0xE0;
+        let edge_ratio = EDGE_ENHANCEMENT_RATIOS[usize::from((self.registers[4] >> 4) & 0x07)];
+
+        let mut processed = vec![0; CAMERA_WIDTH * CAMERA_HEIGHT];
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let (sx, sy) = (x as isize, y as isize);
+                let mut value = sensor_value(sx, sy);
+                if edge_enhancement {
+                    let neighbours = sensor_value(sx - 1, sy)
+                        + sensor_value(sx + 1, sy)
+                        + sensor_value(sx, sy - 1)
+                        + sensor_value(sx, sy + 1);
+                    value += (value * 4.0 - neighbours) * edge_ratio;
+                }
+
+                // every pixel of a 4x4 block has its own 3 thresholds
+                let matrix_index = DITHER_MATRIX_START + ((y & 3) * 4 + (x & 3)) * 3;
+                let thresholds = &self.registers[matrix_index..matrix_index + 3];
+                processed[y * CAMERA_WIDTH + x] = if value < f32::from(thresholds[0]) {
+                    3
+                } else if value < f32::from(thresholds[1]) {
+                    2
+                } else if value < f32::from(thresholds[2]) {
+                    1
+                } else {
+                    0
+                };
+            }
+        }
+        processed
+    }
+}
+
+// used when no image source is set, a diagonal gradient
+fn test_pattern(pixels: &mut [u8]) {
+    for y in 0..CAMERA_HEIGHT {
+        for x in 0..CAMERA_WIDTH {
+            pixels[y * CAMERA_WIDTH + x] = ((x + y) * 255 / (CAMERA_WIDTH + CAMERA_HEIGHT)) as u8;
+        }
+    }
+}
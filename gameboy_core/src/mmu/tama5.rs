@@ -0,0 +1,290 @@
+use super::cartridge::Cartridge;
+use super::mbc::Mbc;
+use crate::emulator::traits::RTC;
+use crate::rtc::Rtc;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// the clock counts years from 00 to 99, every fourth year is a leap year
+const YEARS: u64 = 100;
+const MODE_REGISTER: usize = 0xD;
+const CLOCK_REGISTERS: usize = 0xD;
+
+// the registers are selected by writing to 0xA001 and written through 0xA000,
+// a nibble at a time
+const ROM_BANK_LOW: u8 = 0x0;
+const ROM_BANK_HIGH: u8 = 0x1;
+const DATA_LOW: u8 = 0x4;
+const DATA_HIGH: u8 = 0x5;
+const ADDRESS_HIGH: u8 = 0x6;
+const ADDRESS_LOW: u8 = 0x7;
+const READY: u8 = 0xA;
+const RESULT_LOW: u8 = 0xC;
+const RESULT_HIGH: u8 = 0xD;
+
+pub struct Tama5 {
+    cartridge: Cartridge,
+    selected_rom_bank: usize,
+    selected_register: u8,
+    data: u8,
+    // bit 0 is the top bit of the address, bits 1-3 the command
+    address_high: u8,
+    result: u8,
+    // the TC8521AP's pages 1 to 3 are kept in the cartridge's clock memory,
+    // page 0 is the clock itself
+    clock_mode: u8,
+    rtc: Box<dyn RTC>,
+    ram_change_callback: Box<dyn FnMut(usize, u8)>,
+}
+
+impl Mbc for Tama5 {
+    fn read_byte(&self, index: u16) -> u8 {
+        match index {
+            0x0000..=0x3FFF => {
+                let rom = self.cartridge.get_rom();
+                rom[index as usize]
+            }
+            0x4000..=0x7FFF => {
+                let rom = self.cartridge.get_rom();
+                let offset = self.selected_rom_bank * 0x4000;
+                rom[index as usize - 0x4000 + offset]
+            }
+            0xA000..=0xBFFF => {
+                if index & 0x01 == 0x01 {
+                    return 0xFF;
+                }
+                match self.selected_register {
+                    // games wait for the cartridge to answer this before using it
+                    READY => 0xF1,
+                    RESULT_LOW => 0xF0 | (self.result & 0x0F),
+                    RESULT_HIGH => 0xF0 | (self.result >> 4),
+                    _ => 0xFF,
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn write_byte(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..=0x7FFF => (),
+            0xA000..=0xBFFF => {
+                if index & 0x01 == 0x01 {
+                    self.selected_register = value & 0x0F;
+                } else {
+                    self.write_register(value & 0x0F);
+                }
+            }
+            _ => panic!("index out of range: {:04X}", index),
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn set_ram_change_callback(&mut self, f: Box<dyn FnMut(usize, u8)>) {
+        self.ram_change_callback = f;
+    }
+
+    fn get_rom_bank(&self) -> usize {
+        self.selected_rom_bank
+    }
+}
+
+impl Tama5 {
+    pub fn new(mut cartridge: Cartridge, rtc: Box<dyn RTC>) -> Tama5 {
+        let (rtc_data, last_time) = cartridge.get_last_timestamp();
+        if last_time == 0 {
+            cartridge.set_last_timestamp(rtc_data, rtc.get_current_time());
+        }
+
+        Tama5 {
+            cartridge,
+            selected_rom_bank: 1,
+            selected_register: 0,
+            data: 0,
+            address_high: 0,
+            result: 0,
+            clock_mode: 0,
+            rtc,
+            ram_change_callback: Box::new(|_, _| {}),
+        }
+    }
+
+    fn write_register(&mut self, value: u8) {
+        match self.selected_register {
+            ROM_BANK_LOW => self.set_rom_bank((self.selected_rom_bank & 0x10) | usize::from(value)),
+            ROM_BANK_HIGH => {
+                let high = usize::from(value & 0x01) << 4;
+                self.set_rom_bank((self.selected_rom_bank & 0x0F) | high);
+            }
+            DATA_LOW => self.data = (self.data & 0xF0) | value,
+            DATA_HIGH => self.data = (self.data & 0x0F) | (value << 4),
+            ADDRESS_HIGH => self.address_high = value,
+            // writing the low address bits runs the command
+            ADDRESS_LOW => {
+                let address = (usize::from(self.address_high & 0x01) << 4) | usize::from(value);
+                self.run_command(self.address_high >> 1, address);
+            }
+            _ => (),
+        }
+    }
+
+    fn set_rom_bank(&mut self, bank: usize) {
+        self.selected_rom_bank = bank & (self.cartridge.get_rom_banks() - 1);
+    }
+
+    fn run_command(&mut self, command: u8, address: usize) {
+        match command {
+            0x0 => {
+                let ram = self.cartridge.get_ram_mut();
+                ram[address] = self.data;
+
+                (self.ram_change_callback)(address, self.data);
+            }
+            0x1 => self.result = self.cartridge.get_ram()[address],
+            0x2 => self.write_clock_register(address & 0x0F, self.data & 0x0F),
+            0x4 => self.result = self.read_clock_register(address & 0x0F),
+            _ => (),
+        }
+    }
+
+    // the TC8521AP keeps its registers as decimal digits, page 0 holds the time and date
+    fn read_clock_register(&self, register: usize) -> u8 {
+        if register == MODE_REGISTER {
+            return self.clock_mode;
+        } else if register > MODE_REGISTER {
+            return 0;
+        }
+        let page = usize::from(self.clock_mode & 0x03);
+        if page == 0 {
+            self.get_clock_digits()[register]
+        } else {
+            self.cartridge.get_clock_memory()[(page - 1) * CLOCK_REGISTERS + register]
+        }
+    }
+
+    fn write_clock_register(&mut self, register: usize, value: u8) {
+        if register == MODE_REGISTER {
+            self.clock_mode = value;
+            return;
+        } else if register > MODE_REGISTER {
+            return;
+        }
+        let page = usize::from(self.clock_mode & 0x03);
+        if page == 0 {
+            let mut digits = self.get_clock_digits();
+            digits[register] = value;
+            self.set_clock_digits(&digits);
+        } else {
+            self.cartridge.get_clock_memory_mut()[(page - 1) * CLOCK_REGISTERS + register] = value;
+        }
+    }
+
+    // the clock is kept in the cartridge's rtc data as seconds, minutes and hours,
+    // and the days since the start of year 00, so frontends can save it like the MBC3's
+    fn get_clock(&self) -> u64 {
+        let (rtc_data, last_time) = self.cartridge.get_last_timestamp();
+        let current_time = self.rtc.get_current_time();
+        let elapsed = current_time.saturating_sub(last_time);
+        let days = u64::from(rtc_data.days_low) | (u64::from(rtc_data.days_high) << 8);
+        u64::from(rtc_data.seconds)
+            + u64::from(rtc_data.minutes) * 60
+            + u64::from(rtc_data.hours) * 60 * 60
+            + days * SECONDS_PER_DAY
+            + elapsed
+    }
+
+    fn set_clock(&mut self, seconds: u64) {
+        let days = (seconds / SECONDS_PER_DAY) % Tama5::days_before_year(YEARS);
+        let seconds = seconds % SECONDS_PER_DAY;
+        let rtc_data = Rtc {
+            seconds: (seconds % 60) as u8,
+            minutes: ((seconds / 60) % 60) as u8,
+            hours: (seconds / (60 * 60)) as u8,
+            days_low: days as u8,
+            days_high: (days >> 8) as u8,
+        };
+        let current_time = self.rtc.get_current_time();
+        self.cartridge.set_last_timestamp(rtc_data, current_time);
+    }
+
+    fn get_clock_digits(&self) -> [u8; CLOCK_REGISTERS] {
+        let clock = self.get_clock();
+        let seconds = clock % SECONDS_PER_DAY;
+        let days = (clock / SECONDS_PER_DAY) % Tama5::days_before_year(YEARS);
+        let (year, month, day) = Tama5::get_date(days);
+        let time = [
+            seconds % 60,
+            (seconds / 60) % 60,
+            seconds / (60 * 60),
+            day,
+            month,
+            year,
+        ];
+
+        let mut digits = [0; CLOCK_REGISTERS];
+        for (i, value) in time.iter().enumerate() {
+            let register = if i < 3 { i * 2 } else { i * 2 + 1 };
+            digits[register] = (value % 10) as u8;
+            digits[register + 1] = (value / 10) as u8;
+        }
+        digits[6] = (days % 7) as u8;
+        digits
+    }
+
+    fn set_clock_digits(&mut self, digits: &[u8; CLOCK_REGISTERS]) {
+        let value =
+            |register: usize| u64::from(digits[register]) + u64::from(digits[register + 1]) * 10;
+        let seconds = value(0) + value(2) * 60 + value(4) * 60 * 60;
+        let (year, month, day) = (value(11) % YEARS, value(9), value(7));
+        let month = month.clamp(1, 12);
+        let day = day.clamp(1, Tama5::days_in_month(year, month));
+        let days = Tama5::days_before_year(year)
+            + (1..month)
+                .map(|month| Tama5::days_in_month(year, month))
+                .sum::<u64>()
+            + day
+            - 1;
+        self.set_clock(days * SECONDS_PER_DAY + seconds);
+    }
+
+    fn get_date(mut days: u64) -> (u64, u64, u64) {
+        let mut year = 0;
+        while days >= Tama5::days_in_year(year) {
+            days -= Tama5::days_in_year(year);
+            year += 1;
+        }
+        let mut month = 1;
+        while days >= Tama5::days_in_month(year, month) {
+            days -= Tama5::days_in_month(year, month);
+            month += 1;
+        }
+        (year, month, days + 1)
+    }
+
+    fn days_before_year(year: u64) -> u64 {
+        (0..year).map(Tama5::days_in_year).sum()
+    }
+
+    fn days_in_year(year: u64) -> u64 {
+        if year & 0x03 == 0 {
+            366
+        } else {
+            365
+        }
+    }
+
+    fn days_in_month(year: u64, month: u64) -> u64 {
+        match month {
+            2 if year & 0x03 == 0 => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
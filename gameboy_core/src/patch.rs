@@ -0,0 +1,489 @@
+use std::convert::TryFrom;
+
+// soft patches are applied to the rom before it's loaded,
+// the format is told apart by the patch's header
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+// UPS and BPS patches end with the source, target and patch checksums
+const FOOTER_SIZE: usize = 12;
+// 7 bits per byte, enough for any 64 bit number
+const MAX_NUMBER_BYTES: usize = 10;
+// the largest MBC5 rom, patches can't make anything bigger than this
+const MAX_ROM_SIZE: usize = 8 * 1024 * 1024;
+
+pub fn apply_patch(rom: Vec<u8>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(&rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(&rom, patch)
+    } else {
+        Err(String::from("Unknown patch format"))
+    }
+}
+
+struct PatchReader<'a> {
+    patch: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(patch: &'a [u8], position: usize) -> PatchReader<'a> {
+        PatchReader { patch, position }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = get_range(self.patch, self.position, length)
+            .ok_or_else(|| String::from("Unexpected end of patch"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_big_endian(&mut self, length: usize) -> Result<usize, String> {
+        let bytes = self.read_bytes(length)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | usize::from(byte)))
+    }
+
+    // UPS and BPS numbers are stored 7 bits at a time, the last byte has its top bit set
+    fn read_number(&mut self) -> Result<usize, String> {
+        let too_large = || String::from("Number in patch is too large");
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+        for _ in 0..MAX_NUMBER_BYTES {
+            let byte = self.read_byte()?;
+            let digit = usize::from(byte & 0x7F)
+                .checked_mul(shift)
+                .ok_or_else(too_large)?;
+            number = number.checked_add(digit).ok_or_else(too_large)?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(too_large)?;
+            number = number.checked_add(shift).ok_or_else(too_large)?;
+        }
+        Err(too_large())
+    }
+
+    fn read_signed_number(&mut self) -> Result<isize, String> {
+        let number = self.read_number()?;
+        let magnitude = (number >> 1) as isize;
+        Ok(if number & 1 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        })
+    }
+}
+
+fn apply_ips(mut rom: Vec<u8>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+    loop {
+        let offset_bytes = reader.read_bytes(3)?;
+        if offset_bytes == IPS_EOF {
+            break;
+        }
+        let offset = offset_bytes
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | usize::from(byte));
+        let size = reader.read_big_endian(2)?;
+        // a size of 0 means a run of the same byte
+        let (size, data) = if size == 0 {
+            let size = reader.read_big_endian(2)?;
+            let value = reader.read_byte()?;
+            (size, vec![value; size])
+        } else {
+            (size, reader.read_bytes(size)?.to_vec())
+        };
+        if rom.len() < offset + size {
+            rom.resize(offset + size, 0x00);
+        }
+        rom[offset..offset + size].copy_from_slice(&data);
+    }
+
+    // some patches truncate the rom after the end marker
+    if patch.len() - reader.position >= 3 {
+        let length = reader.read_big_endian(3)?;
+        rom.truncate(length);
+    }
+    Ok(rom)
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (source_crc, target_crc) = verify_patch_crc(patch)?;
+    check_crc(rom, source_crc, "source")?;
+
+    let mut reader = PatchReader::new(patch, UPS_MAGIC.len());
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+    if rom.len() != source_size {
+        return Err(format!(
+            "Patch expects a rom of {} bytes, got {}",
+            source_size,
+            rom.len()
+        ));
+    }
+    check_target_size(target_size)?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0x00);
+    // the patch xors the differences into the rom, each run ends with a 0 byte
+    let mut position: usize = 0;
+    while reader.position < patch.len() - FOOTER_SIZE {
+        position = position
+            .checked_add(reader.read_number()?)
+            .filter(|&position| position <= MAX_ROM_SIZE)
+            .ok_or_else(|| String::from("Patch writes outside of the rom"))?;
+        loop {
+            let value = reader.read_byte()?;
+            if value == 0x00 {
+                position += 1;
+                break;
+            }
+            if position < target.len() {
+                target[position] ^= value;
+            }
+            position += 1;
+        }
+    }
+
+    check_crc(&target, target_crc, "target")?;
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (source_crc, target_crc) = verify_patch_crc(patch)?;
+    check_crc(rom, source_crc, "source")?;
+
+    let mut reader = PatchReader::new(patch, BPS_MAGIC.len());
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+    let metadata_size = reader.read_number()?;
+    reader.read_bytes(metadata_size)?;
+    if rom.len() != source_size {
+        return Err(format!(
+            "Patch expects a rom of {} bytes, got {}",
+            source_size,
+            rom.len()
+        ));
+    }
+    check_target_size(target_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset: isize = 0;
+    let mut target_offset: isize = 0;
+    let out_of_range = || String::from("Patch reads outside of the rom");
+    while reader.position < patch.len() - FOOTER_SIZE {
+        let action = reader.read_number()?;
+        let length = (action >> 2) + 1;
+        // every action adds length bytes, this also keeps the offsets in range
+        if length > target_size - target.len() {
+            return Err(String::from("Patch writes past the end of the rom"));
+        }
+        match action & 0x03 {
+            // source read
+            0 => {
+                let bytes = get_range(rom, target.len(), length).ok_or_else(out_of_range)?;
+                target.extend_from_slice(bytes);
+            }
+            // target read
+            1 => target.extend_from_slice(reader.read_bytes(length)?),
+            // source copy
+            2 => {
+                source_offset = source_offset
+                    .checked_add(reader.read_signed_number()?)
+                    .ok_or_else(out_of_range)?;
+                let start = usize::try_from(source_offset).map_err(|_| out_of_range())?;
+                let bytes = get_range(rom, start, length).ok_or_else(out_of_range)?;
+                target.extend_from_slice(bytes);
+                source_offset += length as isize;
+            }
+            // target copy, which can overlap with what it's writing
+            _ => {
+                target_offset = target_offset
+                    .checked_add(reader.read_signed_number()?)
+                    .ok_or_else(out_of_range)?;
+                for _ in 0..length {
+                    let value = *target
+                        .get(target_offset as usize)
+                        .ok_or_else(out_of_range)?;
+                    target.push(value);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(format!(
+            "Patch produced {} bytes, expected {}",
+            target.len(),
+            target_size
+        ));
+    }
+    check_crc(&target, target_crc, "target")?;
+    Ok(target)
+}
+
+fn get_range(data: &[u8], start: usize, length: usize) -> Option<&[u8]> {
+    let end = start.checked_add(length)?;
+    data.get(start..end)
+}
+
+// the size comes from the patch, check it before allocating anything
+fn check_target_size(target_size: usize) -> Result<(), String> {
+    if target_size > MAX_ROM_SIZE {
+        return Err(format!("Patch makes a rom of {} bytes", target_size));
+    }
+    Ok(())
+}
+
+// returns the source and target checksums
+fn verify_patch_crc(patch: &[u8]) -> Result<(u32, u32), String> {
+    if patch.len() < FOOTER_SIZE + 4 {
+        return Err(String::from("Unexpected end of patch"));
+    }
+    let footer = &patch[patch.len() - FOOTER_SIZE..];
+    let read_u32 =
+        |i: usize| u32::from_le_bytes([footer[i], footer[i + 1], footer[i + 2], footer[i + 3]]);
+    check_crc(&patch[..patch.len() - 4], read_u32(8), "patch")?;
+    Ok((read_u32(0), read_u32(4)))
+}
+
+fn check_crc(data: &[u8], expected: u32, name: &str) -> Result<(), String> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    let crc = hasher.finalize();
+    if crc == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "Wrong {} checksum: expected {:08X}, got {:08X}",
+            name, expected, crc
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    fn encode_number(mut number: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (number & 0x7F) as u8;
+            number >>= 7;
+            if number == 0 {
+                bytes.push(byte | 0x80);
+                return bytes;
+            }
+            bytes.push(byte);
+            number -= 1;
+        }
+    }
+
+    // adds the checksum footer, the source and target checksums are given
+    fn finish_patch(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc(source).to_le_bytes());
+        patch.extend_from_slice(&crc(target).to_le_bytes());
+        let patch_crc = crc(&patch);
+        patch.extend_from_slice(&patch_crc.to_le_bytes());
+        patch
+    }
+
+    fn read_number(bytes: &[u8]) -> Result<usize, String> {
+        PatchReader::new(bytes, 0).read_number()
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        for &number in &[0, 1, 0x7F, 0x80, 0x407F, 0x4080, MAX_ROM_SIZE, usize::MAX] {
+            assert_eq!(read_number(&encode_number(number)), Ok(number));
+        }
+    }
+
+    #[test]
+    fn truncated_number() {
+        assert_eq!(
+            read_number(&[0x00, 0x7F]),
+            Err(String::from("Unexpected end of patch"))
+        );
+    }
+
+    #[test]
+    fn oversized_number() {
+        let too_large = Err(String::from("Number in patch is too large"));
+        // more bytes than any number needs
+        assert_eq!(read_number(&[0x00; 16]), too_large);
+        // ten bytes, but more than 64 bits
+        let mut bytes = vec![0x7F; 9];
+        bytes.push(0xFF);
+        assert_eq!(read_number(&bytes), too_large);
+    }
+
+    #[test]
+    fn ips_records() {
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // a run of 3 0xCC bytes, past the end of the rom
+        patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+        patch.extend_from_slice(IPS_EOF);
+        let rom = apply_patch(vec![0x00; 4], &patch).unwrap();
+        assert_eq!(rom, vec![0x00, 0xAA, 0xBB, 0x00, 0xCC, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn ips_truncates_after_eof() {
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend_from_slice(IPS_EOF);
+        patch.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(apply_patch(vec![0x01; 4], &patch), Ok(vec![0x01, 0x01]));
+    }
+
+    #[test]
+    fn ips_truncated_record() {
+        let mut patch = IPS_MAGIC.to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]);
+        assert_eq!(
+            apply_patch(vec![0x00; 4], &patch),
+            Err(String::from("Unexpected end of patch"))
+        );
+    }
+
+    #[test]
+    fn ups_xors_the_differences() {
+        let source = vec![0x10, 0x20, 0x30, 0x40];
+        let target = vec![0x10, 0x21, 0x30, 0x40, 0x50];
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target.len()));
+        // skip a byte and xor one, the 0 ending the run skips another,
+        // then skip one more and write the new byte
+        patch.extend(encode_number(1));
+        patch.extend_from_slice(&[0x01, 0x00]);
+        patch.extend(encode_number(1));
+        patch.extend_from_slice(&[0x50, 0x00]);
+        let patch = finish_patch(patch, &source, &target);
+        assert_eq!(apply_patch(source, &patch), Ok(target));
+    }
+
+    #[test]
+    fn ups_wrong_source() {
+        let source = vec![0x10, 0x20];
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(encode_number(2));
+        patch.extend(encode_number(2));
+        let patch = finish_patch(patch, &source, &source);
+        assert!(apply_patch(vec![0x11, 0x20], &patch).is_err());
+    }
+
+    #[test]
+    fn ups_oversized_target() {
+        let source = vec![0x10];
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(encode_number(1));
+        patch.extend(encode_number(MAX_ROM_SIZE + 1));
+        let patch = finish_patch(patch, &source, &source);
+        assert!(apply_patch(source, &patch).is_err());
+    }
+
+    fn bps_patch(source: &[u8], target: &[u8], target_size: usize, actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target_size));
+        patch.extend(encode_number(0));
+        patch.extend_from_slice(actions);
+        finish_patch(patch, source, target)
+    }
+
+    fn bps_action(kind: usize, length: usize) -> Vec<u8> {
+        encode_number(((length - 1) << 2) | kind)
+    }
+
+    #[test]
+    fn bps_actions() {
+        let source = vec![0x01, 0x02, 0x03, 0x04];
+        let target = vec![0x01, 0x02, 0xAA, 0x03, 0x04, 0x03, 0x04, 0x03];
+        let mut actions = Vec::new();
+        // source read of 2, target read of 1
+        actions.extend(bps_action(0, 2));
+        actions.extend(bps_action(1, 1));
+        actions.push(0xAA);
+        // source copy of 2 from offset 2
+        actions.extend(bps_action(2, 2));
+        actions.extend(encode_number(2 << 1));
+        // target copy of 3 from offset 3, overlapping what it writes
+        actions.extend(bps_action(3, 3));
+        actions.extend(encode_number(3 << 1));
+        let patch = bps_patch(&source, &target, target.len(), &actions);
+        assert_eq!(apply_patch(source, &patch), Ok(target));
+    }
+
+    #[test]
+    fn bps_negative_offsets() {
+        let source = vec![0x01, 0x02, 0x03, 0x04];
+        let target = vec![0x03, 0x04, 0x02, 0x03];
+        let mut actions = Vec::new();
+        actions.extend(bps_action(2, 2));
+        actions.extend(encode_number(2 << 1));
+        // the source offset is 4 after the copy, go back 3
+        actions.extend(bps_action(2, 2));
+        actions.extend(encode_number((3 << 1) | 1));
+        let patch = bps_patch(&source, &target, target.len(), &actions);
+        assert_eq!(apply_patch(source, &patch), Ok(target));
+    }
+
+    #[test]
+    fn bps_oversized_target() {
+        let source = vec![0x01];
+        let patch = bps_patch(&source, &source, usize::MAX, &[]);
+        assert_eq!(
+            apply_patch(source, &patch),
+            Err(format!("Patch makes a rom of {} bytes", usize::MAX))
+        );
+    }
+
+    #[test]
+    fn bps_action_past_the_target() {
+        let source = vec![0x01, 0x02];
+        // a target copy of a huge length would never stop growing the rom
+        let mut actions = bps_action(3, 1 << 40);
+        actions.extend(encode_number(0));
+        let patch = bps_patch(&source, &source, source.len(), &actions);
+        assert_eq!(
+            apply_patch(source, &patch),
+            Err(String::from("Patch writes past the end of the rom"))
+        );
+    }
+
+    #[test]
+    fn bps_source_copy_before_the_rom() {
+        let source = vec![0x01, 0x02];
+        let mut actions = bps_action(2, 1);
+        actions.extend(encode_number((1 << 1) | 1));
+        let patch = bps_patch(&source, &source, source.len(), &actions);
+        assert_eq!(
+            apply_patch(source, &patch),
+            Err(String::from("Patch reads outside of the rom"))
+        );
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(apply_patch(vec![0x00], b"NOPE").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::cheats::{Cheat, CheatKind};
+
+// the parts of memory games keep their variables in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RamRegion {
+    // all banks, bank 1 and up are mapped to 0xD000 one at a time on the CGB
+    WorkRam,
+    HighRam,
+    // all banks of the cartridge's ram, mapped to 0xA000
+    CartridgeRam,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueSize {
+    Byte,
+    // little endian, like the CPU's 16 bit loads
+    Word,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueFormat {
+    Unsigned,
+    Signed,
+    // one decimal digit per nibble, often used for scores and timers
+    Bcd,
+}
+
+// Changed, Unchanged, Greater and Less compare against the previous snapshot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchFilter {
+    Equal(i64),
+    Changed,
+    Unchanged,
+    Greater,
+    Less,
+}
+
+#[derive(Clone)]
+pub struct RamSnapshot {
+    pub work_ram: Vec<u8>,
+    pub high_ram: Vec<u8>,
+    pub cartridge_ram: Vec<u8>,
+}
+
+impl RamSnapshot {
+    fn get_region(&self, region: RamRegion) -> &[u8] {
+        match region {
+            RamRegion::WorkRam => &self.work_ram,
+            RamRegion::HighRam => &self.high_ram,
+            RamRegion::CartridgeRam => &self.cartridge_ram,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RamCandidate {
+    pub region: RamRegion,
+    pub offset: usize,
+    pub value: i64,
+    pub previous_value: i64,
+}
+
+impl RamCandidate {
+    // the address the candidate shows up at, when its bank is mapped
+    pub fn get_address(&self) -> u16 {
+        match self.region {
+            RamRegion::WorkRam if self.offset < 0x1000 => 0xC000 + self.offset as u16,
+            RamRegion::WorkRam => 0xD000 + (self.offset % 0x1000) as u16,
+            RamRegion::HighRam => 0xFF80 + self.offset as u16,
+            RamRegion::CartridgeRam => 0xA000 + (self.offset % 0x2000) as u16,
+        }
+    }
+
+    pub fn get_bank(&self) -> usize {
+        match self.region {
+            RamRegion::WorkRam => self.offset / 0x1000,
+            RamRegion::HighRam => 0,
+            RamRegion::CartridgeRam => self.offset / 0x2000,
+        }
+    }
+}
+
+// every refinement keeps the candidates that pass the filter,
+// comparing against the values they had in the previous snapshot
+pub struct RamSearch {
+    size: ValueSize,
+    format: ValueFormat,
+    candidates: Vec<RamCandidate>,
+}
+
+impl RamSearch {
+    pub fn new(size: ValueSize, format: ValueFormat, snapshot: &RamSnapshot) -> RamSearch {
+        let mut candidates = Vec::new();
+        let width = RamSearch::get_width(size);
+        for &region in [
+            RamRegion::WorkRam,
+            RamRegion::HighRam,
+            RamRegion::CartridgeRam,
+        ]
+        .iter()
+        {
+            let memory = snapshot.get_region(region);
+            if memory.len() < width {
+                continue;
+            }
+            for offset in 0..=memory.len() - width {
+                let value = RamSearch::read_value(memory, offset, size, format);
+                candidates.push(RamCandidate {
+                    region,
+                    offset,
+                    value,
+                    previous_value: value,
+                });
+            }
+        }
+        RamSearch {
+            size,
+            format,
+            candidates,
+        }
+    }
+
+    pub fn refine(&mut self, filter: SearchFilter, snapshot: &RamSnapshot) {
+        let size = self.size;
+        let format = self.format;
+        self.candidates.retain_mut(|candidate| {
+            let memory = snapshot.get_region(candidate.region);
+            if candidate.offset + RamSearch::get_width(size) > memory.len() {
+                return false;
+            }
+            let value = RamSearch::read_value(memory, candidate.offset, size, format);
+            let previous = candidate.value;
+            candidate.previous_value = previous;
+            candidate.value = value;
+            match filter {
+                SearchFilter::Equal(expected) => value == expected,
+                SearchFilter::Changed => value != previous,
+                SearchFilter::Unchanged => value == previous,
+                SearchFilter::Greater => value > previous,
+                SearchFilter::Less => value < previous,
+            }
+        });
+    }
+
+    pub fn get_candidates(&self) -> &[RamCandidate] {
+        &self.candidates
+    }
+
+    // GameShark codes that keep the candidate at the given value, one per byte
+    pub fn create_freeze_cheats(&self, candidate: &RamCandidate, value: i64) -> Vec<Cheat> {
+        let raw = RamSearch::encode_value(value, self.format);
+        let bytes = match self.size {
+            ValueSize::Byte => vec![raw as u8],
+            ValueSize::Word => vec![raw as u8, (raw >> 8) as u8],
+        };
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| {
+                // a word can end in the next bank, which is mapped somewhere else
+                let location = RamCandidate {
+                    offset: candidate.offset + i,
+                    ..*candidate
+                };
+                let address = location.get_address();
+                let bank = match location.region {
+                    RamRegion::WorkRam if location.get_bank() > 0 => {
+                        0x90 | location.get_bank() as u8
+                    }
+                    RamRegion::CartridgeRam => location.get_bank() as u8,
+                    _ => 0x01,
+                };
+                Cheat {
+                    code: format!(
+                        "{:02X}{:02X}{:02X}{:02X}",
+                        bank,
+                        byte,
+                        address & 0xFF,
+                        address >> 8
+                    ),
+                    description: String::new(),
+                    enabled: true,
+                    kind: CheatKind::GameShark {
+                        bank,
+                        address,
+                        value: byte,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn get_width(size: ValueSize) -> usize {
+        match size {
+            ValueSize::Byte => 1,
+            ValueSize::Word => 2,
+        }
+    }
+
+    fn read_value(memory: &[u8], offset: usize, size: ValueSize, format: ValueFormat) -> i64 {
+        let raw = match size {
+            ValueSize::Byte => u16::from(memory[offset]),
+            ValueSize::Word => u16::from(memory[offset]) | (u16::from(memory[offset + 1]) << 8),
+        };
+        match (format, size) {
+            (ValueFormat::Unsigned, _) => i64::from(raw),
+            (ValueFormat::Signed, ValueSize::Byte) => i64::from(raw as u8 as i8),
+            (ValueFormat::Signed, ValueSize::Word) => i64::from(raw as i16),
+            // invalid digits are read as negative numbers, so they never match a decimal value
+            (ValueFormat::Bcd, _) if (0..4).any(|digit| (raw >> (digit * 4)) & 0x0F > 9) => {
+                -1 - i64::from(raw)
+            }
+            (ValueFormat::Bcd, _) => (0..4).rev().fold(0, |value, digit| {
+                value * 10 + i64::from((raw >> (digit * 4)) & 0x0F)
+            }),
+        }
+    }
+
+    fn encode_value(value: i64, format: ValueFormat) -> u16 {
+        match format {
+            ValueFormat::Unsigned | ValueFormat::Signed => value as u16,
+            ValueFormat::Bcd => (0..4).fold(0, |raw, digit| {
+                let decimal = (value / 10i64.pow(digit)) % 10;
+                raw | ((decimal as u16) << (digit * 4))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_codes(region: RamRegion, offset: usize) -> Vec<String> {
+        let snapshot = RamSnapshot {
+            work_ram: vec![0; 0x8000],
+            high_ram: vec![0; 0x7F],
+            cartridge_ram: vec![0; 0x4000],
+        };
+        let search = RamSearch::new(ValueSize::Word, ValueFormat::Unsigned, &snapshot);
+        let candidate = search
+            .get_candidates()
+            .iter()
+            .find(|candidate| candidate.region == region && candidate.offset == offset)
+            .unwrap();
+        search
+            .create_freeze_cheats(candidate, 0x1234)
+            .into_iter()
+            .map(|cheat| cheat.code)
+            .collect()
+    }
+
+    #[test]
+    fn word_in_one_bank() {
+        assert_eq!(
+            get_codes(RamRegion::CartridgeRam, 0x2010),
+            vec!["013410A0", "011211A0"]
+        );
+        assert_eq!(
+            get_codes(RamRegion::HighRam, 0x10),
+            vec!["013490FF", "011291FF"]
+        );
+    }
+
+    #[test]
+    fn word_across_cartridge_ram_banks() {
+        assert_eq!(
+            get_codes(RamRegion::CartridgeRam, 0x1FFF),
+            vec!["0034FFBF", "011200A0"]
+        );
+    }
+
+    #[test]
+    fn word_across_work_ram_banks() {
+        assert_eq!(
+            get_codes(RamRegion::WorkRam, 0x0FFF),
+            vec!["0134FFCF", "911200D0"]
+        );
+        assert_eq!(
+            get_codes(RamRegion::WorkRam, 0x1FFF),
+            vec!["9134FFDF", "921200D0"]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ROM_EXTENSIONS: [&str; 2] = [".gb", ".gbc"];
+
+// archives are told apart by their contents, not their file name,
+// anything that isn't a zip or gzip file is used as the rom itself
+pub fn extract_rom(data: Vec<u8>, entry_name: Option<&str>) -> Result<Vec<u8>, String> {
+    if data.starts_with(&ZIP_MAGIC) {
+        extract_zip_entry(data, entry_name)
+    } else if data.starts_with(&GZIP_MAGIC) {
+        let mut rom = Vec::new();
+        GzDecoder::new(data.as_slice())
+            .read_to_end(&mut rom)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(rom)
+    } else {
+        Ok(data)
+    }
+}
+
+// without an entry name the first .gb or .gbc file is used,
+// the name can be the entry's full path or just its file name
+fn extract_zip_entry(data: Vec<u8>, entry_name: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|e| format!("{:?}", e))?;
+    let index = (0..archive.len())
+        .find(|&i| match archive.by_index(i) {
+            Ok(file) => !file.is_dir() && is_selected_entry(file.name(), entry_name),
+            Err(_) => false,
+        })
+        .ok_or_else(|| match entry_name {
+            Some(entry_name) => format!("No entry named {} in the archive", entry_name),
+            None => String::from("No .gb or .gbc file in the archive"),
+        })?;
+
+    let mut file = archive.by_index(index).map_err(|e| format!("{:?}", e))?;
+    let mut rom = Vec::new();
+    file.read_to_end(&mut rom).map_err(|e| format!("{:?}", e))?;
+    Ok(rom)
+}
+
+fn is_selected_entry(name: &str, entry_name: Option<&str>) -> bool {
+    match entry_name {
+        Some(entry_name) => name == entry_name || name.rsplit('/').next() == Some(entry_name),
+        None => {
+            let name = name.to_lowercase();
+            ROM_EXTENSIONS
+                .iter()
+                .any(|extension| name.ends_with(extension))
+        }
+    }
+}
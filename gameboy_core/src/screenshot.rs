@@ -0,0 +1,94 @@
+use crate::emulator::traits::PixelMapper;
+use crate::gpu::cgb_color::CGBColor;
+use crate::gpu::color::Color;
+
+pub const SCREEN_WIDTH: u32 = 160;
+pub const SCREEN_HEIGHT: u32 = 144;
+const FRAME_SIZE: usize = (SCREEN_WIDTH * SCREEN_HEIGHT * 3) as usize;
+
+// keeps an RGB24 copy of everything the GPU draws, so screenshots
+// don't depend on what the frontend does with the pixels
+pub struct FrameCapture {
+    drawing: Vec<u8>,
+    finished: Vec<u8>,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            drawing: vec![0xFF; FRAME_SIZE],
+            finished: vec![0xFF; FRAME_SIZE],
+        }
+    }
+
+    fn set_pixel(&mut self, pixel: usize, color_bytes: [u8; 3]) {
+        self.drawing[pixel * 3..pixel * 3 + 3].copy_from_slice(&color_bytes);
+    }
+
+    // called on vblank, a screenshot never shows a half drawn frame
+    pub fn finish_frame(&mut self) {
+        self.finished.copy_from_slice(&self.drawing);
+    }
+
+    pub fn get_frame(&self) -> &[u8] {
+        &self.finished
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, String> {
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_data, SCREEN_WIDTH, SCREEN_HEIGHT);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(|e| format!("{:?}", e))?;
+            writer
+                .write_image_data(&self.finished)
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        Ok(png_data)
+    }
+}
+
+impl Default for FrameCapture {
+    fn default() -> FrameCapture {
+        FrameCapture::new()
+    }
+}
+
+// forwards every pixel to the frontend's pixel mapper after capturing it
+pub struct CapturingPixelMapper<'a, P: PixelMapper> {
+    frame_capture: &'a mut FrameCapture,
+    system: &'a mut P,
+}
+
+impl<'a, P: PixelMapper> CapturingPixelMapper<'a, P> {
+    pub fn new(
+        frame_capture: &'a mut FrameCapture,
+        system: &'a mut P,
+    ) -> CapturingPixelMapper<'a, P> {
+        CapturingPixelMapper {
+            frame_capture,
+            system,
+        }
+    }
+}
+
+impl<'a, P: PixelMapper> PixelMapper for CapturingPixelMapper<'a, P> {
+    fn map_pixel(&mut self, pixel: usize, color: Color) {
+        // same shades the frontends use
+        let color_bytes = match color {
+            Color::White => [255, 255, 255],
+            Color::LightGray => [178, 178, 178],
+            Color::DarkGray => [102, 102, 102],
+            Color::Black => [0, 0, 0],
+        };
+        self.frame_capture.set_pixel(pixel, color_bytes);
+        self.system.map_pixel(pixel, color);
+    }
+
+    fn cgb_map_pixel(&mut self, pixel: usize, color: CGBColor) {
+        self.frame_capture
+            .set_pixel(pixel, [color.red, color.green, color.blue]);
+        self.system.cgb_map_pixel(pixel, color);
+    }
+}
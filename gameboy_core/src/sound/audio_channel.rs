@@ -0,0 +1,16 @@
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AudioChannel {
+    Pulse1 = 0,
+    Pulse2 = 1,
+    Wave = 2,
+    Noise = 3,
+}
+
+impl AudioChannel {
+    pub const ALL: [AudioChannel; 4] = [
+        AudioChannel::Pulse1,
+        AudioChannel::Pulse2,
+        AudioChannel::Wave,
+        AudioChannel::Noise,
+    ];
+}
@@ -0,0 +1,144 @@
+// band-limited step synthesis, loosely based on Shay Green's blip_buf
+// amplitude changes are recorded as deltas at the clock they happen on,
+// each delta is spread over the output samples with a windowed sinc kernel,
+// and the samples are integrated back into a waveform when they are read out
+const PHASE_BITS: u32 = 5;
+const PHASE_COUNT: usize = 1 << PHASE_BITS;
+const KERNEL_WIDTH: usize = 16;
+const KERNEL_CUTOFF: f64 = 0.9;
+const FRAC_BITS: u32 = 32;
+const BUFFER_SIZE: usize = 1024;
+
+pub const CLOCK_RATE: u32 = 4_194_304;
+
+pub struct BlipBuffer {
+    kernel: [[f32; KERNEL_WIDTH]; PHASE_COUNT],
+    buffer: [f32; BUFFER_SIZE + KERNEL_WIDTH],
+    factor: u64,
+    offset: u64,
+    integrator: f32,
+    capacitor: f32,
+    charge_factor: f32,
+}
+
+impl BlipBuffer {
+    pub fn new(sample_rate: u32) -> BlipBuffer {
+        let mut blip_buffer = BlipBuffer {
+            kernel: BlipBuffer::build_kernel(),
+            buffer: [0.0; BUFFER_SIZE + KERNEL_WIDTH],
+            factor: 0,
+            offset: 0,
+            integrator: 0.0,
+            capacitor: 0.0,
+            charge_factor: 0.0,
+        };
+        blip_buffer.set_sample_rate(sample_rate);
+        blip_buffer
+    }
+
+    // each phase holds the difference of a band-limited step between
+    // consecutive output samples, for a step starting phase/PHASE_COUNT
+    // of the way between two samples
+    fn build_kernel() -> [[f32; KERNEL_WIDTH]; PHASE_COUNT] {
+        let mut kernel = [[0.0; KERNEL_WIDTH]; PHASE_COUNT];
+        let half_width = (KERNEL_WIDTH / 2) as f64;
+
+        for (phase, taps) in kernel.iter_mut().enumerate() {
+            let fraction = phase as f64 / PHASE_COUNT as f64;
+            let mut values = [0.0f64; KERNEL_WIDTH];
+            let mut sum = 0.0;
+
+            for (i, value) in values.iter_mut().enumerate() {
+                let x = i as f64 - half_width + 0.5 - fraction;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    let angle = std::f64::consts::PI * KERNEL_CUTOFF * x;
+                    angle.sin() / angle
+                };
+                // blackman window stretched over the width of the kernel
+                let position = ((x + half_width) / KERNEL_WIDTH as f64).clamp(0.0, 1.0);
+                let window = 0.42 - 0.5 * (2.0 * std::f64::consts::PI * position).cos()
+                    + 0.08 * (4.0 * std::f64::consts::PI * position).cos();
+                *value = KERNEL_CUTOFF * sinc * window;
+                sum += *value;
+            }
+
+            // normalize, so a full step always adds up to exactly the delta
+            for (tap, value) in taps.iter_mut().zip(values.iter()) {
+                *tap = (value / sum) as f32;
+            }
+        }
+
+        kernel
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let factor = ((sample_rate as u64) << FRAC_BITS) as f64 / CLOCK_RATE as f64;
+        self.factor = factor.ceil() as u64;
+        // the gameboy's output capacitor charges at 0.999958 per clock,
+        // scale that to the length of one output sample
+        let clocks_per_sample = CLOCK_RATE as f64 / sample_rate as f64;
+        self.charge_factor = 0.999_958f64.powf(clocks_per_sample) as f32;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer = [0.0; BUFFER_SIZE + KERNEL_WIDTH];
+        self.offset = 0;
+        self.integrator = 0.0;
+        self.capacitor = 0.0;
+    }
+
+    pub fn align_to(&mut self, other: &BlipBuffer) {
+        self.offset = other.offset;
+    }
+
+    // time is the number of clocks since the start of the current frame
+    pub fn add_delta(&mut self, time: u32, delta: f32) {
+        let position = self.offset + time as u64 * self.factor;
+        let index = (position >> FRAC_BITS) as usize;
+        let phase = ((position >> (FRAC_BITS - PHASE_BITS)) as usize) & (PHASE_COUNT - 1);
+
+        if index < BUFFER_SIZE {
+            let taps = &self.kernel[phase];
+            for (sample, tap) in self.buffer[index..index + KERNEL_WIDTH]
+                .iter_mut()
+                .zip(taps.iter())
+            {
+                *sample += delta * tap;
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self, clocks: u32) {
+        self.offset += clocks as u64 * self.factor;
+    }
+
+    pub fn samples_available(&self) -> usize {
+        (self.offset >> FRAC_BITS) as usize
+    }
+
+    // writes samples into every stride'th element of out,
+    // returns how many samples were written
+    pub fn read_samples(&mut self, out: &mut [f32], stride: usize) -> usize {
+        let count = std::cmp::min(self.samples_available(), out.len().div_ceil(stride));
+
+        for i in 0..count {
+            self.integrator += self.buffer[i];
+            // dc blocking high-pass filter, like the capacitor on the real hardware
+            let input = self.integrator;
+            let output = input - self.capacitor;
+            self.capacitor = input - output * self.charge_factor;
+            out[i * stride] = output;
+        }
+
+        self.buffer.copy_within(count.., 0);
+        for sample in self.buffer.iter_mut().rev().take(count) {
+            *sample = 0.0;
+        }
+        self.offset -= (count as u64) << FRAC_BITS;
+
+        count
+    }
+}
@@ -1,11 +1,18 @@
 use crate::bit_utils;
 
+pub mod audio_channel;
+mod blip_buffer;
 mod noise_channel;
 mod pulse_channel;
+mod stereo_output;
+pub mod wav_writer;
 mod wave_channel;
 
+use self::audio_channel::AudioChannel;
 use self::noise_channel::NoiseChannel;
 use self::pulse_channel::PulseChannel;
+use self::stereo_output::StereoOutput;
+use self::wav_writer::{WavOutput, WavWriter};
 use self::wave_channel::WaveChannel;
 
 const READ_BYTE_OR_MASKS: [u8; 23] = [
@@ -13,8 +20,10 @@ const READ_BYTE_OR_MASKS: [u8; 23] = [
     0xff, 0x00, 0x00, 0xbf, 0x00, 0x00, 0x70,
 ];
 const SAMPLE_SIZE: usize = 4096;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
 
 pub struct Sound {
+    is_cgb: bool,
     wave_channel: WaveChannel,
     pulse_channel_1: PulseChannel,
     pulse_channel_2: PulseChannel,
@@ -29,14 +38,21 @@ pub struct Sound {
     power_control: bool,
     frame_sequence_count_down: i32,
     frame_sequencer: u8,
-    down_sample_count: i32,
+    sample_rate: u32,
+    output: StereoOutput,
     buffer_fill_amount: usize,
+    muted_channels: [bool; 4],
+    soloed_channels: [bool; 4],
+    channel_outputs: Vec<StereoOutput>,
+    channel_buffers: Vec<[f32; SAMPLE_SIZE]>,
+    wav_writer: Option<WavWriter>,
 }
 
 impl Sound {
-    pub fn new() -> Sound {
+    pub fn new(is_cgb: bool) -> Sound {
         Sound {
-            wave_channel: WaveChannel::new(),
+            is_cgb,
+            wave_channel: WaveChannel::new(is_cgb),
             pulse_channel_1: PulseChannel::new(),
             pulse_channel_2: PulseChannel::new(),
             noise_channel: NoiseChannel::new(),
@@ -50,16 +66,20 @@ impl Sound {
             power_control: false,
             frame_sequence_count_down: 8192,
             frame_sequencer: 0,
-            down_sample_count: 95,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            output: StereoOutput::new(DEFAULT_SAMPLE_RATE),
             buffer_fill_amount: 0,
+            muted_channels: [false; 4],
+            soloed_channels: [false; 4],
+            channel_outputs: Vec::new(),
+            channel_buffers: Vec::new(),
+            wav_writer: None,
         }
     }
 
     pub fn step(&mut self, cycles: i32) -> bool {
         let mut audio_buffer_full = false;
-        let mut cycles = cycles;
-        while cycles != 0 {
-            cycles -= 1;
+        for time in 0..cycles as u32 {
             self.frame_sequence_count_down -= 1;
             if self.frame_sequence_count_down == 0 {
                 self.frame_sequence_count_down = 8192;
@@ -95,81 +115,147 @@ impl Sound {
             self.wave_channel.step();
             self.noise_channel.step();
 
-            self.down_sample_count -= 1;
-            if self.down_sample_count <= 0 {
-                self.down_sample_count = 95;
-
-                // left
-                let mut bufferin_0 = 0.0;
-                let mut bufferin_1;
-                let volume = (128 * self.vin_l_volume as i32) / 7;
-                if self.left_enables[0] {
-                    bufferin_1 = (self.pulse_channel_1.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                if self.left_enables[1] {
-                    bufferin_1 = (self.pulse_channel_2.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                if self.left_enables[2] {
-                    bufferin_1 = (self.wave_channel.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                if self.left_enables[3] {
-                    bufferin_1 = (self.noise_channel.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                self.audio_buffer[self.buffer_fill_amount] = bufferin_0;
+            let outputs = [
+                self.pulse_channel_1.get_output_vol(),
+                self.pulse_channel_2.get_output_vol(),
+                self.wave_channel.get_output_vol(),
+                self.noise_channel.get_output_vol(),
+            ];
+            let any_soloed = self.soloed_channels.iter().any(|soloed| *soloed);
 
-                // right
-                bufferin_0 = 0.0;
-                if self.right_enables[0] {
-                    bufferin_1 = (self.pulse_channel_1.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                if self.right_enables[1] {
-                    bufferin_1 = (self.pulse_channel_2.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
-                }
-                if self.right_enables[2] {
-                    bufferin_1 = (self.wave_channel.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
+            let mut left_amplitude = 0.0;
+            let mut right_amplitude = 0.0;
+            for (i, output) in outputs.iter().enumerate() {
+                let left = self.channel_amplitude(*output, self.left_enables[i], self.vin_l_volume);
+                let right =
+                    self.channel_amplitude(*output, self.right_enables[i], self.vin_r_volume);
+
+                // the separate channel outputs ignore mute and solo,
+                // so they always add up to what the game is playing
+                if let Some(channel_output) = self.channel_outputs.get_mut(i) {
+                    channel_output.update(time, left, right);
                 }
-                if self.right_enables[3] {
-                    bufferin_1 = (self.noise_channel.get_output_vol() as f32) / 100.0;
-                    Sound::mix_audio(&mut bufferin_0, bufferin_1, volume);
+
+                let audible = if any_soloed {
+                    self.soloed_channels[i]
+                } else {
+                    !self.muted_channels[i]
+                };
+                if audible {
+                    left_amplitude += left;
+                    right_amplitude += right;
                 }
-                self.audio_buffer[self.buffer_fill_amount + 1] = bufferin_0;
-                self.buffer_fill_amount += 2;
             }
+            self.output.update(time, left_amplitude, right_amplitude);
+        }
 
-            if self.buffer_fill_amount >= SAMPLE_SIZE {
-                self.buffer_fill_amount = 0;
-                audio_buffer_full = true;
-                break;
-            }
+        self.output.end_frame(cycles as u32);
+        for channel_output in self.channel_outputs.iter_mut() {
+            channel_output.end_frame(cycles as u32);
+        }
+
+        let fill = self.buffer_fill_amount;
+        let frames_read = self.output.read_samples(&mut self.audio_buffer[fill..]);
+        for (channel_output, channel_buffer) in self
+            .channel_outputs
+            .iter_mut()
+            .zip(self.channel_buffers.iter_mut())
+        {
+            channel_output.read_samples(&mut channel_buffer[fill..]);
+        }
+        if let Some(ref mut wav_writer) = self.wav_writer {
+            wav_writer.write_samples(&self.audio_buffer[fill..fill + frames_read * 2]);
+        }
+        self.buffer_fill_amount += frames_read * 2;
+
+        if self.buffer_fill_amount >= SAMPLE_SIZE {
+            self.buffer_fill_amount = 0;
+            audio_buffer_full = true;
         }
         audio_buffer_full
     }
 
-    // copied from the SDL2 SDL_MixAudioFormat function
-    // adapted for mixing two individual floats
-    fn mix_audio(dst: &mut f32, src: f32, volume: i32) {
-        let fmax_volume = 1.0f32 / 128f32;
-        let fvolume = volume as f32;
-        let max_audioval = std::f32::MAX as f64;
-        let min_audioval = std::f32::MIN as f64;
-
-        let src1 = src * fvolume * fmax_volume;
-        let src2 = *dst;
-
-        let mut dst_sample = src1 as f64 + src2 as f64;
-        if dst_sample > max_audioval {
-            dst_sample = max_audioval;
-        } else if dst_sample < min_audioval {
-            dst_sample = min_audioval;
+    fn channel_amplitude(&self, output: u8, enabled: bool, volume: u8) -> f32 {
+        if enabled {
+            (f32::from(output) / 100.0) * f32::from(volume) / 7.0
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        // a wav file can only have one sample rate, so a running recording ends here
+        if sample_rate != self.sample_rate {
+            self.stop_recording().ok();
+        }
+        self.sample_rate = sample_rate;
+        self.output.set_sample_rate(sample_rate);
+        for channel_output in self.channel_outputs.iter_mut() {
+            channel_output.set_sample_rate(sample_rate);
         }
-        *dst = dst_sample as f32;
+        self.buffer_fill_amount = 0;
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_channel_muted(&mut self, channel: AudioChannel, muted: bool) {
+        self.muted_channels[channel as usize] = muted;
+    }
+
+    pub fn is_channel_muted(&self, channel: AudioChannel) -> bool {
+        self.muted_channels[channel as usize]
+    }
+
+    // while any channel is soloed, only the soloed channels are mixed
+    pub fn set_channel_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+        self.soloed_channels[channel as usize] = soloed;
+    }
+
+    pub fn is_channel_soloed(&self, channel: AudioChannel) -> bool {
+        self.soloed_channels[channel as usize]
+    }
+
+    // when enabled, every channel is also written into its own buffer,
+    // with the same layout and timing as the mixed audio buffer
+    pub fn set_channel_buffers_enabled(&mut self, enabled: bool) {
+        if enabled && self.channel_outputs.is_empty() {
+            for _ in AudioChannel::ALL.iter() {
+                let mut channel_output = StereoOutput::new(self.sample_rate);
+                channel_output.align_to(&self.output);
+                self.channel_outputs.push(channel_output);
+                self.channel_buffers.push([0.0; SAMPLE_SIZE]);
+            }
+        } else if !enabled {
+            self.channel_outputs.clear();
+            self.channel_buffers.clear();
+        }
+    }
+
+    pub fn get_channel_buffer(&self, channel: AudioChannel) -> Option<&[f32]> {
+        self.channel_buffers
+            .get(channel as usize)
+            .map(|buffer| buffer.as_ref())
+    }
+
+    // records the mixed output at the current sample rate,
+    // a recording that is already running is finished first
+    pub fn start_recording(&mut self, output: Box<dyn WavOutput>) -> Result<(), String> {
+        self.stop_recording()?;
+        self.wav_writer = Some(WavWriter::new(output, self.sample_rate)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), String> {
+        match self.wav_writer.take() {
+            Some(wav_writer) => wav_writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.wav_writer.is_some()
     }
 
     pub fn read_byte(&self, address: u16) -> u8 {
@@ -234,16 +320,50 @@ impl Sound {
         }
     }
 
+    // reads a register as it'll be cycles from now, without running the sound,
+    // only wave ram changes fast enough for that to matter
+    pub fn read_byte_ahead(&self, address: u16, cycles: i32) -> u8 {
+        match address {
+            0xFF30..=0xFF3F if cycles > 0 => {
+                let mut wave_channel = self.wave_channel.clone();
+                for _ in 0..cycles {
+                    wave_channel.step();
+                }
+                wave_channel.read_byte(address)
+            }
+            _ => self.read_byte(address),
+        }
+    }
+
     pub fn write_byte(&mut self, address: u16, value: u8) {
         if !self.power_control && address != 0xFF26 && address < 0xFF30 {
+            // the DMG still lets the length counters be loaded while powered off
+            if !self.is_cgb {
+                match address {
+                    0xFF11 => self.pulse_channel_1.set_length(value),
+                    0xFF16 => self.pulse_channel_2.set_length(value),
+                    0xFF1B => self.wave_channel.set_length(value),
+                    0xFF20 => self.noise_channel.set_length(value),
+                    _ => (),
+                }
+            }
             return;
         }
+        let frame_sequencer = self.frame_sequencer;
         match address {
-            0xFF10..=0xFF14 => self.pulse_channel_1.write_byte(address, value),
+            0xFF10..=0xFF14 => self
+                .pulse_channel_1
+                .write_byte(address, value, frame_sequencer),
             0xFF15 => (),
-            0xFF16..=0xFF19 => self.pulse_channel_2.write_byte(address, value),
-            0xFF1A..=0xFF1E => self.wave_channel.write_byte(address, value),
-            0xFF1F..=0xFF23 => self.noise_channel.write_byte(address, value),
+            0xFF16..=0xFF19 => self
+                .pulse_channel_2
+                .write_byte(address, value, frame_sequencer),
+            0xFF1A..=0xFF1E => self
+                .wave_channel
+                .write_byte(address, value, frame_sequencer),
+            0xFF1F..=0xFF23 => self
+                .noise_channel
+                .write_byte(address, value, frame_sequencer),
             0xFF24 => {
                 self.vin_l_enable = bit_utils::is_set(value, 7);
                 self.vin_l_volume = (value >> 4) & 0x7;
@@ -262,29 +382,38 @@ impl Sound {
             }
             0xFF26 => {
                 if !bit_utils::is_set(value, 7) {
-                    for i in 0xFF10..=0xFF25 {
-                        self.write_byte(i, 0);
+                    if self.power_control {
+                        self.power_off();
                     }
-                    self.pulse_channel_1.reset_length_counter();
-                    self.pulse_channel_2.reset_length_counter();
-                    self.wave_channel.reset_length_counter();
-                    self.noise_channel.reset_length_counter();
-                    self.power_control = false;
                 } else if !self.power_control {
+                    // the next frame sequencer step after powering on is always 0
                     self.frame_sequencer = 0;
-                    // reset the wave table
-                    for i in 0xFF30..=0xFF3F {
-                        self.wave_channel.write_byte(i, 0);
-                    }
                     self.power_control = true;
                 }
             }
             0xFF27..=0xFF2F => (),
-            0xFF30..=0xFF3F => self.wave_channel.write_byte(address, value),
+            0xFF30..=0xFF3F => self
+                .wave_channel
+                .write_byte(address, value, frame_sequencer),
             _ => panic!("unknown address: {:04X}", address),
         }
     }
 
+    // clears every register from NR10 to NR51, wave ram is left alone
+    fn power_off(&mut self) {
+        self.pulse_channel_1.power_off(self.is_cgb);
+        self.pulse_channel_2.power_off(self.is_cgb);
+        self.wave_channel.power_off(self.is_cgb);
+        self.noise_channel.power_off(self.is_cgb);
+        self.vin_l_enable = false;
+        self.vin_l_volume = 0;
+        self.vin_r_enable = false;
+        self.vin_r_volume = 0;
+        self.left_enables = [false; 4];
+        self.right_enables = [false; 4];
+        self.power_control = false;
+    }
+
     pub fn get_audio_buffer(&self) -> &[f32] {
         self.audio_buffer.as_ref()
     }
@@ -292,6 +421,6 @@ impl Sound {
 
 impl Default for Sound {
     fn default() -> Sound {
-        Sound::new()
+        Sound::new(false)
     }
 }
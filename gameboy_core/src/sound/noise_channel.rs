@@ -51,12 +51,15 @@ impl NoiseChannel {
         self.timer -= 1;
         if self.timer <= 0 {
             self.timer = DIVISORS[self.divisor_code as usize] << self.clock_shift;
-            let result = (self.lfsr & 0x1) ^ ((self.lfsr >> 1) & 0x1);
-            self.lfsr >>= 1;
-            self.lfsr |= result << 14;
-            if self.width_mode {
-                self.lfsr &= !0x40;
-                self.lfsr |= result << 6;
+            // the lfsr doesn't get clocked at all with a shift of 14 or 15
+            if self.clock_shift < 14 {
+                let result = (self.lfsr & 0x1) ^ ((self.lfsr >> 1) & 0x1);
+                self.lfsr >>= 1;
+                self.lfsr |= result << 14;
+                if self.width_mode {
+                    self.lfsr &= !0x40;
+                    self.lfsr |= result << 6;
+                }
             }
             if self.enabled && self.dac_enabled && (self.lfsr & 0x1) == 0 {
                 self.output_vol = self.volume;
@@ -118,15 +121,32 @@ impl NoiseChannel {
         }
     }
 
-    pub fn write_byte(&mut self, address: u16, value: u8) {
+    pub fn write_byte(&mut self, address: u16, value: u8, frame_sequencer: u8) {
         match address {
             0xFF1F => (),
-            0xFF20 => self.length_load = value & 0x3F,
+            0xFF20 => self.set_length(value),
             0xFF21 => {
+                let envelope_add_mode = bit_utils::is_set(value, 3);
+                // "zombie mode", see PulseChannel::write_byte
+                if self.enabled {
+                    if self.envelope_period_load == 0 && self.envelope_running {
+                        self.volume += 1;
+                    } else if !self.envelope_add_mode {
+                        self.volume += 2;
+                    }
+                    if self.envelope_add_mode != envelope_add_mode {
+                        self.volume = 16u8.wrapping_sub(self.volume);
+                    }
+                    self.volume &= 0x0F;
+                }
+
                 self.dac_enabled = (value & 0xF8) != 0;
                 self.volume_load = (value >> 4) & 0x0F;
-                self.envelope_add_mode = bit_utils::is_set(value, 3);
+                self.envelope_add_mode = envelope_add_mode;
                 self.envelope_period_load = value & 0x07;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
             }
             0xFF22 => {
                 self.divisor_code = value & 0x07;
@@ -134,34 +154,59 @@ impl NoiseChannel {
                 self.clock_shift = (value >> 4) & 0x0F;
             }
             0xFF23 => {
-                self.length_enable = bit_utils::is_set(value, 6);
+                let length_enable = bit_utils::is_set(value, 6);
                 self.trigger_bit = bit_utils::is_set(value, 7);
+
+                let first_half = bit_utils::is_set(frame_sequencer, 0);
+                if !self.length_enable && length_enable && first_half && self.length_counter > 0 {
+                    self.length_counter -= 1;
+                    if self.length_counter == 0 && !self.trigger_bit {
+                        self.enabled = false;
+                    }
+                }
+                self.length_enable = length_enable;
+
                 if self.trigger_bit {
-                    self.trigger();
+                    self.trigger(first_half);
                 }
             }
             _ => panic!("unknown address: {:04X}", address),
         }
     }
 
-    fn trigger(&mut self) {
+    pub fn set_length(&mut self, value: u8) {
+        self.length_load = value & 0x3F;
+        self.length_counter = 64 - self.length_load;
+    }
+
+    fn trigger(&mut self, first_half: bool) {
         self.enabled = true;
         if self.length_counter == 0 {
             self.length_counter = 64;
+            if self.length_enable && first_half {
+                self.length_counter -= 1;
+            }
         }
         self.timer = DIVISORS[self.divisor_code as usize] << self.clock_shift;
         self.envelope_period = self.envelope_period_load as i32;
         self.envelope_running = true;
         self.volume = self.volume_load;
         self.lfsr = 0x7FFF;
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
     }
 
     pub fn get_status(&self) -> bool {
-        self.enabled && self.dac_enabled
+        self.enabled
     }
 
-    pub fn reset_length_counter(&mut self) {
-        self.length_counter = 0;
+    pub fn power_off(&mut self, is_cgb: bool) {
+        let length_counter = self.length_counter;
+        *self = NoiseChannel::new();
+        if !is_cgb {
+            self.length_counter = length_counter;
+        }
     }
 
     pub fn get_output_vol(&self) -> u8 {
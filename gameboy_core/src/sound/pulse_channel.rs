@@ -29,6 +29,7 @@ pub struct PulseChannel {
     sweep_shadow: u16,
     sweep_enable: bool,
     sweep_period: i32,
+    sweep_negate_used: bool,
     output_vol: u8,
     sequence_pointer: u32,
 }
@@ -57,6 +58,7 @@ impl PulseChannel {
             sweep_shadow: 0,
             enabled: false,
             sweep_period: 0,
+            sweep_negate_used: false,
             output_vol: 0,
             sequence_pointer: 0,
         }
@@ -100,9 +102,9 @@ impl PulseChannel {
                 if new_freq <= 2047 && self.sweep_shift > 0 {
                     self.sweep_shadow = new_freq;
                     self.timer_load = new_freq;
+                    // the new frequency is checked for an overflow a second time
                     self.sweep_calculation();
                 }
-                self.sweep_calculation();
             }
         }
     }
@@ -150,42 +152,83 @@ impl PulseChannel {
         }
     }
 
-    pub fn write_byte(&mut self, address: u16, value: u8) {
+    pub fn write_byte(&mut self, address: u16, value: u8, frame_sequencer: u8) {
         match (address & 0xFF & 0xF) % 0x5 {
             0x0 => {
+                let sweep_negate = bit_utils::is_set(value, 3);
+                // leaving negate mode after a negated calculation disables the channel
+                if self.sweep_negate && !sweep_negate && self.sweep_negate_used {
+                    self.enabled = false;
+                }
                 self.sweep_shift = value & 0x07;
-                self.sweep_negate = bit_utils::is_set(value, 3);
+                self.sweep_negate = sweep_negate;
                 self.sweep_period_load = (value >> 4) & 0x07;
             }
             0x1 => {
-                self.length_load = value & 0x3F;
+                self.set_length(value);
                 self.duty = (value >> 6) & 0x3;
             }
             0x2 => {
+                let envelope_add_mode = bit_utils::is_set(value, 3);
+                // "zombie mode", writing the envelope while the channel is playing
+                // changes the volume in the same odd way the hardware does
+                if self.enabled {
+                    if self.envelope_period_load == 0 && self.envelope_running {
+                        self.volume += 1;
+                    } else if !self.envelope_add_mode {
+                        self.volume += 2;
+                    }
+                    if self.envelope_add_mode != envelope_add_mode {
+                        self.volume = 16u8.wrapping_sub(self.volume);
+                    }
+                    self.volume &= 0x0F;
+                }
+
                 self.dac_enabled = (value & 0xF8) != 0;
                 self.volume_load = (value >> 4) & 0x0F;
-                self.envelope_add_mode = bit_utils::is_set(value, 3);
+                self.envelope_add_mode = envelope_add_mode;
                 self.envelope_period_load = value & 0x7;
-                self.envelope_period = self.envelope_period_load as i32;
-                self.volume = self.volume_load;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
             }
             0x3 => self.timer_load = (self.timer_load & 0x700) | value as u16,
             0x4 => {
                 self.timer_load = (self.timer_load & 0xFF) | ((value as u16 & 0x7) << 8);
-                self.length_enable = bit_utils::is_set(value, 6);
+                let length_enable = bit_utils::is_set(value, 6);
                 self.trigger_bit = bit_utils::is_set(value, 7);
-                if bit_utils::is_set(value, 7) {
-                    self.trigger();
+
+                // enabling the length counter in the first half of a length period,
+                // when the next frame sequencer step won't clock it, clocks it once more
+                let first_half = bit_utils::is_set(frame_sequencer, 0);
+                if !self.length_enable && length_enable && first_half && self.length_counter > 0 {
+                    self.length_counter -= 1;
+                    if self.length_counter == 0 && !self.trigger_bit {
+                        self.enabled = false;
+                    }
+                }
+                self.length_enable = length_enable;
+
+                if self.trigger_bit {
+                    self.trigger(first_half);
                 }
             }
             _ => unreachable!(),
         }
     }
 
-    fn trigger(&mut self) {
+    pub fn set_length(&mut self, value: u8) {
+        self.length_load = value & 0x3F;
+        self.length_counter = 64 - self.length_load;
+    }
+
+    fn trigger(&mut self, first_half: bool) {
         self.enabled = true;
         if self.length_counter == 0 {
             self.length_counter = 64;
+            if self.length_enable && first_half {
+                self.length_counter -= 1;
+            }
         }
         self.timer = (2048 - self.timer_load as i32) * 4;
         self.envelope_running = true;
@@ -196,16 +239,21 @@ impl PulseChannel {
         if self.sweep_period == 0 {
             self.sweep_period = 8;
         }
-        self.sweep_enable = self.sweep_period > 0 || self.sweep_shift > 0;
+        self.sweep_enable = self.sweep_period_load > 0 || self.sweep_shift > 0;
+        self.sweep_negate_used = false;
         if self.sweep_shift > 0 {
             self.sweep_calculation();
         }
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
     }
 
     fn sweep_calculation(&mut self) -> u16 {
         let mut new_freq = self.sweep_shadow >> self.sweep_shift;
         if self.sweep_negate {
             new_freq = self.sweep_shadow - new_freq;
+            self.sweep_negate_used = true;
         } else {
             new_freq += self.sweep_shadow;
         }
@@ -216,11 +264,18 @@ impl PulseChannel {
     }
 
     pub fn get_status(&self) -> bool {
-        self.enabled && self.dac_enabled
+        self.enabled
     }
 
-    pub fn reset_length_counter(&mut self) {
-        self.length_counter = 0;
+    // powering the APU off clears every register, the length counter
+    // only survives on the DMG
+    pub fn power_off(&mut self, is_cgb: bool) {
+        let length_counter = self.length_counter;
+        *self = PulseChannel::new();
+        self.dac_enabled = false;
+        if !is_cgb {
+            self.length_counter = length_counter;
+        }
     }
 
     pub fn get_output_vol(&self) -> u8 {
@@ -0,0 +1,62 @@
+use super::blip_buffer::BlipBuffer;
+
+// a pair of blip buffers, one for each side of the stereo output
+pub struct StereoOutput {
+    left_blip: BlipBuffer,
+    right_blip: BlipBuffer,
+    left_amplitude: f32,
+    right_amplitude: f32,
+}
+
+impl StereoOutput {
+    pub fn new(sample_rate: u32) -> StereoOutput {
+        StereoOutput {
+            left_blip: BlipBuffer::new(sample_rate),
+            right_blip: BlipBuffer::new(sample_rate),
+            left_amplitude: 0.0,
+            right_amplitude: 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.left_blip.set_sample_rate(sample_rate);
+        self.right_blip.set_sample_rate(sample_rate);
+        self.left_amplitude = 0.0;
+        self.right_amplitude = 0.0;
+    }
+
+    // makes the output produce its samples at the same points in time as other
+    pub fn align_to(&mut self, other: &StereoOutput) {
+        self.left_blip.align_to(&other.left_blip);
+        self.right_blip.align_to(&other.right_blip);
+    }
+
+    // only changes in amplitude are handed to the blip buffers,
+    // they take care of turning the steps into band-limited samples
+    pub fn update(&mut self, time: u32, left_amplitude: f32, right_amplitude: f32) {
+        if left_amplitude != self.left_amplitude {
+            self.left_blip
+                .add_delta(time, left_amplitude - self.left_amplitude);
+            self.left_amplitude = left_amplitude;
+        }
+
+        if right_amplitude != self.right_amplitude {
+            self.right_blip
+                .add_delta(time, right_amplitude - self.right_amplitude);
+            self.right_amplitude = right_amplitude;
+        }
+    }
+
+    pub fn end_frame(&mut self, clocks: u32) {
+        self.left_blip.end_frame(clocks);
+        self.right_blip.end_frame(clocks);
+    }
+
+    // left and right samples are interleaved in out,
+    // returns the number of stereo frames that were written
+    pub fn read_samples(&mut self, out: &mut [f32]) -> usize {
+        let frames = self.left_blip.read_samples(out, 2);
+        self.right_blip.read_samples(&mut out[1..], 2);
+        frames
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{Seek, SeekFrom, Write};
+
+// samples are stored exactly as the mixer produces them, as 32 bit floats
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const CHANNELS: u16 = 2;
+const BYTES_PER_SAMPLE: u16 = 4;
+const RIFF_SIZE_OFFSET: u64 = 4;
+const FACT_FRAMES_OFFSET: u64 = 46;
+const DATA_SIZE_OFFSET: u64 = 54;
+const HEADER_SIZE: u32 = 58;
+
+pub trait WavOutput: Write + Seek {}
+
+impl<T: Write + Seek> WavOutput for T {}
+
+// writes interleaved stereo samples to a wav file,
+// the sizes in the header are filled in once the recording is finished
+pub struct WavWriter {
+    output: Box<dyn WavOutput>,
+    frames_written: u32,
+    error: Option<std::io::Error>,
+}
+
+impl WavWriter {
+    pub fn new(mut output: Box<dyn WavOutput>, sample_rate: u32) -> Result<WavWriter, String> {
+        WavWriter::write_header(&mut *output, sample_rate).map_err(|e| format!("{:?}", e))?;
+        Ok(WavWriter {
+            output,
+            frames_written: 0,
+            error: None,
+        })
+    }
+
+    fn write_header(output: &mut dyn WavOutput, sample_rate: u32) -> std::io::Result<()> {
+        let block_align = CHANNELS * BYTES_PER_SAMPLE;
+        output.write_all(b"RIFF")?;
+        output.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        output.write_all(b"WAVE")?;
+
+        output.write_all(b"fmt ")?;
+        output.write_all(&18u32.to_le_bytes())?;
+        output.write_all(&FORMAT_IEEE_FLOAT.to_le_bytes())?;
+        output.write_all(&CHANNELS.to_le_bytes())?;
+        output.write_all(&sample_rate.to_le_bytes())?;
+        output.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+        output.write_all(&block_align.to_le_bytes())?;
+        output.write_all(&(BYTES_PER_SAMPLE * 8).to_le_bytes())?;
+        output.write_all(&0u16.to_le_bytes())?;
+
+        // non pcm formats need a fact chunk with the number of frames
+        output.write_all(b"fact")?;
+        output.write_all(&4u32.to_le_bytes())?;
+        output.write_all(&0u32.to_le_bytes())?;
+
+        output.write_all(b"data")?;
+        output.write_all(&0u32.to_le_bytes())?;
+        Ok(())
+    }
+
+    // the first error is kept and handed back by finish,
+    // nothing else is written after it
+    pub fn write_samples(&mut self, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut bytes = Vec::with_capacity(samples.len() * BYTES_PER_SAMPLE as usize);
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        match self.output.write_all(&bytes) {
+            Ok(()) => self.frames_written += (samples.len() / CHANNELS as usize) as u32,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(e) = self.error.take() {
+            return Err(format!("{:?}", e));
+        }
+        self.write_sizes().map_err(|e| format!("{:?}", e))
+    }
+
+    fn write_sizes(&mut self) -> std::io::Result<()> {
+        let data_size = self.frames_written * u32::from(CHANNELS * BYTES_PER_SAMPLE);
+        self.output.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.output
+            .write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.output.seek(SeekFrom::Start(FACT_FRAMES_OFFSET))?;
+        self.output.write_all(&self.frames_written.to_le_bytes())?;
+        self.output.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.output.write_all(&data_size.to_le_bytes())?;
+        self.output.seek(SeekFrom::End(0))?;
+        self.output.flush()
+    }
+}
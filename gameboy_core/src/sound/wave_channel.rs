@@ -1,6 +1,8 @@
 use crate::bit_utils;
 
+#[derive(Clone)]
 pub struct WaveChannel {
+    is_cgb: bool,
     dac_enabled: bool,
     length_load: u8,
     timer_load: u16,
@@ -12,12 +14,15 @@ pub struct WaveChannel {
     enabled: bool,
     timer: i32,
     position_counter: u8,
+    sample_buffer: u8,
+    cycles_since_read: i32,
     output_vol: u8,
 }
 
 impl WaveChannel {
-    pub fn new() -> WaveChannel {
+    pub fn new(is_cgb: bool) -> WaveChannel {
         WaveChannel {
+            is_cgb,
             dac_enabled: false,
             length_load: 0,
             timer_load: 0,
@@ -29,18 +34,23 @@ impl WaveChannel {
             enabled: false,
             timer: 0,
             position_counter: 0,
+            sample_buffer: 0,
+            cycles_since_read: 0,
             output_vol: 0,
         }
     }
 
     pub fn step(&mut self) {
+        self.cycles_since_read += 1;
         self.timer -= 1;
         if self.timer <= 0 {
             self.timer = (2048 - self.timer_load as i32) * 2;
             self.position_counter = (self.position_counter + 1) & 0x1F;
             if self.enabled && self.dac_enabled {
                 let position = self.position_counter / 2;
-                let mut output_byte = self.wave_table[position as usize];
+                self.sample_buffer = self.wave_table[position as usize];
+                self.cycles_since_read = 0;
+                let mut output_byte = self.sample_buffer;
                 let high_bit = !bit_utils::is_set(self.position_counter, 0);
                 if high_bit {
                     output_byte >>= 4;
@@ -81,48 +91,233 @@ impl WaveChannel {
                 let trigger_bit = if self.trigger_bit { 1 } else { 0 };
                 ((self.timer_load >> 8) & 0x07) as u8 | (length_enable << 6) | (trigger_bit << 7)
             }
-            0xFF30..=0xFF3F => self.wave_table[(address - 0xFF30) as usize],
+            0xFF30..=0xFF3F => {
+                // while the channel is playing, wave ram accesses go to the byte the
+                // channel is currently reading, which the DMG only allows right as
+                // the channel reads it
+                if self.enabled {
+                    if self.is_cgb || self.cycles_since_read < 2 {
+                        self.wave_table[(self.position_counter / 2) as usize]
+                    } else {
+                        0xFF
+                    }
+                } else {
+                    self.wave_table[(address - 0xFF30) as usize]
+                }
+            }
             _ => panic!("unknown address: {:04X}", address),
         }
     }
 
-    pub fn write_byte(&mut self, address: u16, value: u8) {
+    pub fn write_byte(&mut self, address: u16, value: u8, frame_sequencer: u8) {
         match address {
-            0xFF1A => self.dac_enabled = bit_utils::is_set(value, 7),
-            0xFF1B => self.length_load = value,
+            0xFF1A => {
+                self.dac_enabled = bit_utils::is_set(value, 7);
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            0xFF1B => self.set_length(value),
             0xFF1C => self.volume_code = (value >> 5) & 0x03,
             0xFF1D => self.timer_load = (self.timer_load & 0x700) | value as u16,
             0xFF1E => {
                 self.timer_load = (self.timer_load & 0xFF) | ((value as u16 & 0x7) << 8);
-                self.length_enable = bit_utils::is_set(value, 6);
+                let length_enable = bit_utils::is_set(value, 6);
                 self.trigger_bit = bit_utils::is_set(value, 7);
+
+                let first_half = bit_utils::is_set(frame_sequencer, 0);
+                if !self.length_enable && length_enable && first_half && self.length_counter > 0 {
+                    self.length_counter -= 1;
+                    if self.length_counter == 0 && !self.trigger_bit {
+                        self.enabled = false;
+                    }
+                }
+                self.length_enable = length_enable;
+
                 if self.trigger_bit {
-                    self.trigger();
+                    self.trigger(first_half);
+                }
+            }
+            0xFF30..=0xFF3F => {
+                if self.enabled {
+                    if self.is_cgb || self.cycles_since_read < 2 {
+                        self.wave_table[(self.position_counter / 2) as usize] = value;
+                    }
+                } else {
+                    self.wave_table[(address - 0xFF30) as usize] = value;
                 }
             }
-            0xFF30..=0xFF3F => self.wave_table[(address - 0xFF30) as usize] = value,
             _ => panic!("unknown address: {:04X}", address),
         }
     }
 
-    fn trigger(&mut self) {
+    pub fn set_length(&mut self, value: u8) {
+        self.length_load = value;
+        self.length_counter = 256 - u16::from(value);
+    }
+
+    fn trigger(&mut self, first_half: bool) {
+        // retriggering on the DMG right as the channel reads a sample
+        // corrupts the first bytes of wave ram
+        if !self.is_cgb && self.enabled && self.timer <= 2 {
+            let position = (((self.position_counter + 1) & 0x1F) / 2) as usize;
+            if position < 4 {
+                self.wave_table[0] = self.wave_table[position];
+            } else {
+                let block = position & !0x03;
+                self.wave_table.copy_within(block..block + 4, 0);
+            }
+        }
+
         self.enabled = true;
         if self.length_counter == 0 {
             self.length_counter = 256;
+            if self.length_enable && first_half {
+                self.length_counter -= 1;
+            }
         }
-        self.timer = (2048 - self.timer_load as i32) * 2;
+        // the first sample is read a few cycles later than a normal period
+        self.timer = (2048 - self.timer_load as i32) * 2 + 6;
         self.position_counter = 0;
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
     }
 
     pub fn get_status(&self) -> bool {
-        self.enabled && self.dac_enabled
+        self.enabled
     }
 
-    pub fn reset_length_counter(&mut self) {
-        self.length_counter = 0;
+    // wave ram isn't touched when the APU is powered off
+    pub fn power_off(&mut self, is_cgb: bool) {
+        let length_counter = self.length_counter;
+        let wave_table = self.wave_table;
+        *self = WaveChannel::new(is_cgb);
+        self.wave_table = wave_table;
+        if !is_cgb {
+            self.length_counter = length_counter;
+        }
     }
 
     pub fn get_output_vol(&self) -> u8 {
         self.output_vol
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a period of 512 cycles, so the first sample is read after 518
+    const FREQUENCY: u16 = 0x700;
+    const FIRST_READ: usize = 518;
+    const PERIOD: usize = 512;
+
+    fn triggered_channel(is_cgb: bool) -> WaveChannel {
+        let mut channel = WaveChannel::new(is_cgb);
+        for i in 0..16 {
+            channel.write_byte(0xFF30 + i, 0x10 + i as u8, 0);
+        }
+        channel.write_byte(0xFF1A, 0x80, 0);
+        channel.write_byte(0xFF1C, 0x20, 0);
+        channel.write_byte(0xFF1D, (FREQUENCY & 0xFF) as u8, 0);
+        channel.write_byte(0xFF1E, 0x80 | (FREQUENCY >> 8) as u8, 0);
+        channel
+    }
+
+    fn step(channel: &mut WaveChannel, cycles: usize) {
+        for _ in 0..cycles {
+            channel.step();
+        }
+    }
+
+    // turning the dac off stops the channel, so wave ram can be read directly
+    fn wave_table(mut channel: WaveChannel) -> Vec<u8> {
+        channel.write_byte(0xFF1A, 0x00, 0);
+        (0xFF30..=0xFF3F)
+            .map(|address| channel.read_byte(address))
+            .collect()
+    }
+
+    #[test]
+    fn first_sample_is_read_late() {
+        let mut channel = triggered_channel(false);
+        step(&mut channel, FIRST_READ - 1);
+        assert_eq!(channel.position_counter, 0);
+        step(&mut channel, 1);
+        assert_eq!(channel.position_counter, 1);
+        step(&mut channel, PERIOD);
+        assert_eq!(channel.position_counter, 2);
+    }
+
+    #[test]
+    fn dmg_reads_only_as_the_channel_reads() {
+        let mut channel = triggered_channel(false);
+        step(&mut channel, FIRST_READ - 1);
+        assert_eq!(channel.read_byte(0xFF30), 0xFF);
+        step(&mut channel, 1);
+        assert_eq!(channel.read_byte(0xFF3F), 0x10);
+        step(&mut channel, 1);
+        assert_eq!(channel.read_byte(0xFF3F), 0x10);
+        step(&mut channel, 1);
+        assert_eq!(channel.read_byte(0xFF30), 0xFF);
+    }
+
+    #[test]
+    fn dmg_writes_only_as_the_channel_reads() {
+        let mut channel = triggered_channel(false);
+        step(&mut channel, FIRST_READ + PERIOD);
+        channel.write_byte(0xFF30, 0xAA, 0);
+        step(&mut channel, 2);
+        channel.write_byte(0xFF30, 0xBB, 0);
+        // the channel is on the 3rd sample, both writes go to its byte
+        let table = wave_table(channel);
+        assert_eq!(table[..2], [0x10, 0xAA]);
+    }
+
+    #[test]
+    fn cgb_reads_the_current_byte() {
+        let mut channel = triggered_channel(true);
+        step(&mut channel, FIRST_READ + PERIOD * 2 + 10);
+        assert_eq!(channel.read_byte(0xFF30), 0x11);
+    }
+
+    #[test]
+    fn dmg_retrigger_corrupts_wave_ram() {
+        let mut channel = triggered_channel(false);
+        // the next read is of the 9th sample, in byte 4
+        step(&mut channel, FIRST_READ + PERIOD * 7 + PERIOD - 2);
+        channel.write_byte(0xFF1E, 0x80 | (FREQUENCY >> 8) as u8, 0);
+        let table = wave_table(channel);
+        assert_eq!(table[..4], [0x14, 0x15, 0x16, 0x17]);
+        assert_eq!(table[4..8], [0x14, 0x15, 0x16, 0x17]);
+    }
+
+    #[test]
+    fn dmg_retrigger_corrupts_the_first_byte() {
+        let mut channel = triggered_channel(false);
+        // the next read is of the 3rd sample, in byte 1
+        step(&mut channel, FIRST_READ + PERIOD * 2 - 2);
+        channel.write_byte(0xFF1E, 0x80 | (FREQUENCY >> 8) as u8, 0);
+        let table = wave_table(channel);
+        assert_eq!(table[..4], [0x11, 0x11, 0x12, 0x13]);
+    }
+
+    #[test]
+    fn dmg_retrigger_away_from_a_read() {
+        let mut channel = triggered_channel(false);
+        step(&mut channel, FIRST_READ + PERIOD * 7 + PERIOD - 3);
+        channel.write_byte(0xFF1E, 0x80 | (FREQUENCY >> 8) as u8, 0);
+        let table = wave_table(channel);
+        assert_eq!(table[..4], [0x10, 0x11, 0x12, 0x13]);
+    }
+
+    #[test]
+    fn cgb_retrigger_doesnt_corrupt() {
+        let mut channel = triggered_channel(true);
+        step(&mut channel, FIRST_READ + PERIOD * 7 + PERIOD - 2);
+        channel.write_byte(0xFF1E, 0x80 | (FREQUENCY >> 8) as u8, 0);
+        let table = wave_table(channel);
+        assert_eq!(table[..4], [0x10, 0x11, 0x12, 0x13]);
+    }
+}
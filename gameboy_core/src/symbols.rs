@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+// a label from an RGBDS or no$gmb .sym file, the bank only matters
+// for addresses in rom, like for breakpoints
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub bank: usize,
+    pub address: u16,
+    pub name: String,
+}
+
+impl Symbol {
+    pub fn matches(&self, rom_bank: usize, address: u16) -> bool {
+        if self.address != address {
+            return false;
+        }
+        match address {
+            0x0000..=0x3FFF => self.bank == 0,
+            0x4000..=0x7FFF => self.bank == rom_bank,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    by_address: HashMap<u16, Vec<usize>>,
+    by_name: HashMap<String, usize>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    pub fn add_symbol(&mut self, symbol: Symbol) {
+        let index = self.symbols.len();
+        self.by_address
+            .entry(symbol.address)
+            .or_default()
+            .push(index);
+        self.by_name.insert(symbol.name.clone(), index);
+        self.symbols.push(symbol);
+    }
+
+    pub fn get_symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|&index| &self.symbols[index])
+    }
+
+    pub fn get_label(&self, rom_bank: usize, address: u16) -> Option<&str> {
+        self.by_address
+            .get(&address)?
+            .iter()
+            .map(|&index| &self.symbols[index])
+            .find(|symbol| symbol.matches(rom_bank, address))
+            .map(|symbol| symbol.name.as_str())
+    }
+
+    // the closest label before the address, in the same memory area,
+    // like PlayerUpdate+$12, or just the address if there's none
+    pub fn format_address(&self, rom_bank: usize, address: u16) -> String {
+        let area_start = get_area_start(address);
+        let closest = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.address <= address && symbol.address >= area_start)
+            .filter(|symbol| symbol.matches(rom_bank, symbol.address))
+            .max_by_key(|symbol| symbol.address);
+        match closest {
+            Some(symbol) if symbol.address == address => symbol.name.clone(),
+            Some(symbol) => format!("{}+${:X}", symbol.name, address - symbol.address),
+            None => format!("${:04X}", address),
+        }
+    }
+}
+
+fn get_area_start(address: u16) -> u16 {
+    match address {
+        0x0000..=0x3FFF => 0x0000,
+        0x4000..=0x7FFF => 0x4000,
+        0x8000..=0x9FFF => 0x8000,
+        0xA000..=0xBFFF => 0xA000,
+        0xC000..=0xCFFF => 0xC000,
+        0xD000..=0xDFFF => 0xD000,
+        0xE000..=0xFDFF => 0xE000,
+        0xFE00..=0xFEFF => 0xFE00,
+        0xFF00..=0xFF7F => 0xFF00,
+        _ => 0xFF80,
+    }
+}
+
+// lines look like "01:4A2F PlayerUpdate", everything after a ; is a comment
+pub fn parse_symbol_file(contents: &str) -> Result<SymbolTable, String> {
+    let mut symbols = SymbolTable::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = match line.find(';') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+        let mut parts = line.split_whitespace();
+        let (location, name) = match (parts.next(), parts.next()) {
+            (Some(location), Some(name)) => (location, name),
+            (None, _) => continue,
+            (Some(_), None) => return Err(format!("line {}: missing label", line_number + 1)),
+        };
+        let symbol = parse_location(location)
+            .map(|(bank, address)| Symbol {
+                bank,
+                address,
+                name: name.to_string(),
+            })
+            .ok_or_else(|| format!("line {}: invalid address {}", line_number + 1, location))?;
+        symbols.add_symbol(symbol);
+    }
+    Ok(symbols)
+}
+
+fn parse_location(location: &str) -> Option<(usize, u16)> {
+    let mut parts = location.splitn(2, ':');
+    let bank = usize::from_str_radix(parts.next()?, 16).ok()?;
+    let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+    Some((bank, address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(bank: usize, address: u16, name: &str) -> Symbol {
+        Symbol {
+            bank,
+            address,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn symbol_file() {
+        let contents = "; File generated by rgblink\n00:0150 Main\n01:4000 Bank1Start ; entry\n\n";
+        let symbols = parse_symbol_file(contents).unwrap();
+        assert_eq!(
+            symbols.get_symbols(),
+            &[symbol(0, 0x0150, "Main"), symbol(1, 0x4000, "Bank1Start")]
+        );
+        assert_eq!(
+            symbols.find("Bank1Start"),
+            Some(&symbol(1, 0x4000, "Bank1Start"))
+        );
+        assert_eq!(symbols.find("Missing"), None);
+    }
+
+    #[test]
+    fn symbol_file_errors() {
+        assert_eq!(
+            parse_symbol_file("00:0150 Main\n00:0160").err(),
+            Some(String::from("line 2: missing label"))
+        );
+        assert_eq!(
+            parse_symbol_file("zz:0150 Main").err(),
+            Some(String::from("line 1: invalid address zz:0150"))
+        );
+        assert_eq!(
+            parse_symbol_file("0150 Main").err(),
+            Some(String::from("line 1: invalid address 0150"))
+        );
+    }
+
+    #[test]
+    fn labels_by_bank() {
+        let symbols = parse_symbol_file("01:4000 One\n02:4000 Two\n00:C000 Ram\n").unwrap();
+        assert_eq!(symbols.get_label(2, 0x4000), Some("Two"));
+        assert_eq!(symbols.get_label(3, 0x4000), None);
+        // outside of the rom the bank doesn't matter
+        assert_eq!(symbols.get_label(5, 0xC000), Some("Ram"));
+    }
+
+    #[test]
+    fn formatted_addresses() {
+        let symbols = parse_symbol_file("00:0150 Main\n01:4000 One\n00:C100 Buffer\n").unwrap();
+        assert_eq!(symbols.format_address(1, 0x0150), "Main");
+        assert_eq!(symbols.format_address(1, 0x0162), "Main+$12");
+        assert_eq!(symbols.format_address(1, 0x4ABC), "One+$ABC");
+        // the label is in another bank
+        assert_eq!(symbols.format_address(2, 0x4ABC), "$4ABC");
+        // the closest label is in another memory area
+        assert_eq!(symbols.format_address(1, 0x8000), "$8000");
+        assert_eq!(symbols.format_address(1, 0xC0FF), "$C0FF");
+        assert_eq!(symbols.format_address(1, 0xC101), "Buffer+$1");
+    }
+}
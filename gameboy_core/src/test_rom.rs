@@ -0,0 +1,47 @@
+// blargg's test roms write their result to cartridge ram, the signature
+// tells it apart from garbage left in the ram
+const SIGNATURE_ADDRESS: u16 = 0xA001;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const STATUS_ADDRESS: u16 = 0xA000;
+const TEXT_ADDRESS: u16 = 0xA004;
+// the status is 0x80 while the tests run, and 0x81 when a reset is needed
+const RUNNING: u8 = 0x80;
+const RESET_REQUIRED: u8 = 0x81;
+
+pub struct TestRomResult {
+    // 0 when every test passed, otherwise the number of the failed test
+    pub code: u8,
+    // what the rom printed, the names of the tests and their results
+    pub output: String,
+}
+
+impl TestRomResult {
+    pub fn passed(&self) -> bool {
+        self.code == 0
+    }
+}
+
+pub fn read_test_rom_result(read_byte: impl Fn(u16) -> u8) -> Option<TestRomResult> {
+    let has_signature = SIGNATURE
+        .iter()
+        .enumerate()
+        .all(|(i, &byte)| read_byte(SIGNATURE_ADDRESS + i as u16) == byte);
+    if !has_signature {
+        return None;
+    }
+
+    let code = read_byte(STATUS_ADDRESS);
+    if code == RUNNING || code == RESET_REQUIRED {
+        return None;
+    }
+
+    let mut output = String::new();
+    for address in TEXT_ADDRESS..0xC000 {
+        let byte = read_byte(address);
+        if byte == 0 {
+            break;
+        }
+        output.push(byte as char);
+    }
+    Some(TestRomResult { code, output })
+}
@@ -0,0 +1,220 @@
+use crate::cpu::registers::Registers;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+// an address range to trace, a bank of None matches every bank,
+// the bank is only checked for addresses in rom like breakpoints
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceFilter {
+    pub bank: Option<usize>,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl TraceFilter {
+    pub fn new(bank: Option<usize>, start: u16, end: u16) -> TraceFilter {
+        TraceFilter { bank, start, end }
+    }
+
+    pub fn matches(&self, rom_bank: usize, address: u16) -> bool {
+        if address < self.start || address > self.end {
+            return false;
+        }
+        match (self.bank, address) {
+            (Some(bank), 0x4000..=0x7FFF) => bank == rom_bank,
+            (Some(bank), 0x0000..=0x3FFF) => bank == 0,
+            _ => true,
+        }
+    }
+}
+
+// where the lines go, shared with TraceHandle so a panic hook can
+// still save them when the emulator can't be reached anymore
+struct TraceOutput {
+    output: Option<Box<dyn Write + Send>>,
+    history: VecDeque<String>,
+    capacity: usize,
+    error: Option<std::io::Error>,
+}
+
+impl TraceOutput {
+    // the first error is kept and handed back by finish,
+    // nothing else is written after it
+    fn write_line(&mut self, line: String) {
+        match self.output {
+            Some(ref mut output) => {
+                if self.error.is_none() {
+                    if let Err(e) = writeln!(output, "{}", line) {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            None => {
+                if self.capacity == 0 {
+                    return;
+                }
+                if self.history.len() == self.capacity {
+                    self.history.pop_front();
+                }
+                self.history.push_back(line);
+            }
+        }
+    }
+}
+
+// a panic can happen while the lines are being written, the
+// lines are still worth saving then
+fn lock_output(output: &Mutex<TraceOutput>) -> Option<MutexGuard<'_, TraceOutput>> {
+    match output.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+// logs every instruction before it runs, in the gameboy-doctor format,
+// either to a writer or to a ring buffer of the last lines
+pub struct Tracer {
+    output: Arc<Mutex<TraceOutput>>,
+    filters: Vec<TraceFilter>,
+    // labels get a line of their own, so the other lines still match gameboy-doctor's
+    show_labels: bool,
+}
+
+impl Tracer {
+    pub fn new(output: Box<dyn Write + Send>) -> Tracer {
+        Tracer::with_output(TraceOutput {
+            output: Some(output),
+            history: VecDeque::new(),
+            capacity: 0,
+            error: None,
+        })
+    }
+
+    pub fn with_ring_buffer(capacity: usize) -> Tracer {
+        Tracer::with_output(TraceOutput {
+            output: None,
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            error: None,
+        })
+    }
+
+    fn with_output(output: TraceOutput) -> Tracer {
+        Tracer {
+            output: Arc::new(Mutex::new(output)),
+            filters: Vec::new(),
+            show_labels: false,
+        }
+    }
+
+    pub fn set_show_labels(&mut self, show_labels: bool) {
+        self.show_labels = show_labels;
+    }
+
+    pub fn is_showing_labels(&self) -> bool {
+        self.show_labels
+    }
+
+    // with no filters every instruction is traced
+    pub fn add_filter(&mut self, filter: TraceFilter) {
+        self.filters.push(filter);
+    }
+
+    pub fn get_handle(&self) -> TraceHandle {
+        TraceHandle {
+            output: self.output.clone(),
+        }
+    }
+
+    pub fn get_history(&self) -> Vec<String> {
+        self.get_handle().get_history()
+    }
+
+    pub fn trace(
+        &mut self,
+        rom_bank: usize,
+        registers: &Registers,
+        label: Option<&str>,
+        read_byte: impl Fn(u16) -> u8,
+    ) {
+        let pc = registers.pc;
+        if !self.filters.is_empty()
+            && !self
+                .filters
+                .iter()
+                .any(|filter| filter.matches(rom_bank, pc))
+        {
+            return;
+        }
+        let line = format_line(registers, read_byte);
+        if let Some(mut output) = lock_output(&self.output) {
+            if let Some(label) = label {
+                output.write_line(format!("{}:", label));
+            }
+            output.write_line(line);
+        }
+    }
+
+    // returns the lines kept by a ring buffer, a writer only gets flushed
+    pub fn finish(self) -> Result<Vec<String>, String> {
+        let mut output = match lock_output(&self.output) {
+            Some(output) => output,
+            None => return Ok(Vec::new()),
+        };
+        if let Some(e) = output.error.take() {
+            return Err(format!("{:?}", e));
+        }
+        if let Some(ref mut writer) = output.output {
+            writer.flush().map_err(|e| format!("{:?}", e))?;
+        }
+        Ok(output.history.drain(..).collect())
+    }
+}
+
+// reaches a running trace without the emulator, for panic hooks
+#[derive(Clone)]
+pub struct TraceHandle {
+    output: Arc<Mutex<TraceOutput>>,
+}
+
+impl TraceHandle {
+    pub fn get_history(&self) -> Vec<String> {
+        match lock_output(&self.output) {
+            Some(output) => output.history.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        match lock_output(&self.output) {
+            Some(mut output) => match output.output {
+                Some(ref mut writer) => writer.flush(),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+fn format_line(registers: &Registers, read_byte: impl Fn(u16) -> u8) -> String {
+    let pc = registers.pc;
+    format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+        registers.a,
+        registers.get_af() as u8,
+        registers.b,
+        registers.c,
+        registers.d,
+        registers.e,
+        registers.h,
+        registers.l,
+        registers.sp,
+        pc,
+        read_byte(pc),
+        read_byte(pc.wrapping_add(1)),
+        read_byte(pc.wrapping_add(2)),
+        read_byte(pc.wrapping_add(3)),
+    )
+}
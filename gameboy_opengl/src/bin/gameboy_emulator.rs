@@ -2,8 +2,11 @@
 extern crate clap;
 
 use clap::{App, Arg};
-use std::fs::File;
+use gameboy_core::TraceFilter;
+use gameboy_opengl::{Options, VideoFormat};
+use std::fs::{self, File};
 use std::io::Read;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), String> {
     let matches = App::new(crate_name!())
@@ -12,10 +15,90 @@ fn main() -> Result<(), String> {
         .about(crate_description!())
         .arg(
             Arg::with_name("rom filename")
-                .help("rom file to use")
+                .help("rom file to use, can be a .zip or .gz archive")
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("rom entry")
+                .help("file to load from a .zip archive, instead of the first .gb or .gbc file")
+                .long("rom-entry")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("patch")
+                .help("ips, ups or bps patch to apply, can be given more than once. without it, the first .ips, .ups or .bps file next to the rom is used")
+                .long("patch")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("video format")
+                .help("format used when recording video")
+                .long("video-format")
+                .takes_value(true)
+                .possible_values(&["y4m", "png"])
+                .default_value("y4m"),
+        )
+        .arg(
+            Arg::with_name("camera image")
+                .help("png image the Pocket Camera sees, instead of a test pattern")
+                .long("camera-image")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .help("start in the debugger, F7 also stops in it while playing")
+                .long("debug"),
+        )
+        .arg(
+            Arg::with_name("gdb port")
+                .help("wait for gdb to attach on this port, over the gdb remote protocol")
+                .long("gdb")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .help("log every instruction to this file, in the gameboy-doctor format")
+                .long("trace")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace last")
+                .help("only keep the last instructions, written out when the emulator stops or crashes")
+                .long("trace-last")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace labels")
+                .help("put labels from the symbol file in the trace, on lines of their own")
+                .long("trace-labels"),
+        )
+        .arg(
+            Arg::with_name("symbols")
+                .help("RGBDS symbol file, a .sym file next to the rom is used by default")
+                .long("symbols")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace filter")
+                .help("only trace instructions in a hex address range, like 4000-7FFF or 2:4000-7FFF")
+                .long("trace-filter")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("crash report")
+                .help("follow the call stack, so it's printed when the emulator crashes")
+                .long("crash-report"),
+        )
+        .arg(
+            Arg::with_name("test rom")
+                .help("run a blargg test rom without a window, print what it printed and exit with its result")
+                .long("test-rom"),
+        )
         .get_matches();
 
     let rom_filename = matches.value_of("rom filename").unwrap();
@@ -23,7 +106,92 @@ fn main() -> Result<(), String> {
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)
         .map_err(|e| format!("{:?}", e))?;
-    gameboy_opengl::start(buffer)?;
+    let mut rom = gameboy_core::extract_rom(buffer, matches.value_of("rom entry"))?;
+    let patch_filenames: Vec<PathBuf> = match matches.values_of("patch") {
+        Some(patch_filenames) => patch_filenames.map(PathBuf::from).collect(),
+        None => find_patch(Path::new(rom_filename)).into_iter().collect(),
+    };
+    for patch_filename in patch_filenames {
+        let patch = fs::read(&patch_filename).map_err(|e| format!("{:?}", e))?;
+        rom = gameboy_core::apply_patch(rom, &patch)
+            .map_err(|e| format!("{}: {}", patch_filename.display(), e))?;
+    }
+    if matches.is_present("test rom") {
+        let result = gameboy_opengl::run_test_rom(rom)?;
+        println!("{}", result.output);
+        if !result.passed() {
+            return Err(format!("failed with code {}", result.code));
+        }
+        return Ok(());
+    }
+    let video_format = match matches.value_of("video format") {
+        Some("png") => VideoFormat::Png,
+        _ => VideoFormat::Y4m,
+    };
+    let camera_image = matches.value_of("camera image").map(PathBuf::from);
+    let gdb_port = match matches.value_of("gdb port") {
+        Some(port) => Some(port.parse::<u16>().map_err(|e| format!("{:?}", e))?),
+        None => None,
+    };
+    let trace_last = match matches.value_of("trace last") {
+        Some(count) => Some(count.parse::<usize>().map_err(|e| format!("{:?}", e))?),
+        None => None,
+    };
+    let trace_filters = match matches.values_of("trace filter") {
+        Some(filters) => filters
+            .map(parse_trace_filter)
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let symbol_file = match matches.value_of("symbols") {
+        Some(symbol_file) => Some(PathBuf::from(symbol_file)),
+        None => Some(Path::new(rom_filename).with_extension("sym"))
+            .filter(|symbol_file| symbol_file.is_file()),
+    };
+    let options = Options {
+        video_format,
+        camera_image,
+        debug: matches.is_present("debug"),
+        gdb_port,
+        trace_file: matches.value_of("trace").map(PathBuf::from),
+        trace_last,
+        trace_filters,
+        trace_labels: matches.is_present("trace labels"),
+        symbol_file,
+        crash_report: matches.is_present("crash report"),
+    };
+    gameboy_opengl::start(rom, options)?;
 
     Ok(())
 }
+
+// a patch named after the rom, like game.ips for game.gb, several of them
+// are usually alternatives for the same change, so only the first is used
+fn find_patch(rom_filename: &Path) -> Option<PathBuf> {
+    ["ips", "ups", "bps"]
+        .iter()
+        .map(|extension| rom_filename.with_extension(extension))
+        .find(|patch_filename| patch_filename.is_file())
+}
+
+// an optional rom bank, then an inclusive range, all in hex: 2:4000-7FFF
+fn parse_trace_filter(filter: &str) -> Result<TraceFilter, String> {
+    let invalid = || format!("invalid trace filter: {}", filter);
+    let (bank, range) = match filter.find(':') {
+        Some(index) => {
+            let bank = usize::from_str_radix(&filter[..index], 16).map_err(|_| invalid())?;
+            (Some(bank), &filter[index + 1..])
+        }
+        None => (None, filter),
+    };
+    let mut addresses = range.splitn(2, '-');
+    let start = addresses
+        .next()
+        .and_then(|start| u16::from_str_radix(start, 16).ok())
+        .ok_or_else(invalid)?;
+    let end = match addresses.next() {
+        Some(end) => u16::from_str_radix(end, 16).map_err(|_| invalid())?,
+        None => start,
+    };
+    Ok(TraceFilter::new(bank, start, end))
+}
@@ -0,0 +1,127 @@
+use gameboy_core::{DebugImage, Gameboy};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+const WINDOW_SCALE: u32 = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DebugView {
+    TileData,
+    TileMap(usize),
+    Sprites,
+    Palettes,
+}
+
+impl DebugView {
+    fn get_title(self) -> &'static str {
+        match self {
+            DebugView::TileData => "Tile Data",
+            DebugView::TileMap(0) => "Tile Map 0x9800",
+            DebugView::TileMap(_) => "Tile Map 0x9C00",
+            DebugView::Sprites => "Sprites",
+            DebugView::Palettes => "Palettes",
+        }
+    }
+
+    fn render(self, emulator: &Gameboy) -> DebugImage {
+        match self {
+            DebugView::TileData => emulator.render_tile_data(),
+            DebugView::TileMap(map) => emulator.render_tile_map(map),
+            DebugView::Sprites => emulator.render_sprites(),
+            DebugView::Palettes => emulator.render_palettes(),
+        }
+    }
+}
+
+struct DebugWindow {
+    view: DebugView,
+    canvas: Canvas<Window>,
+    // with unsafe_textures the texture isn't tied to the canvas' lifetime,
+    // it's freed along with the canvas' renderer
+    texture: Texture,
+}
+
+// secondary windows showing what's in video memory, they're redrawn every frame
+pub struct DebugWindows {
+    video_subsystem: VideoSubsystem,
+    windows: Vec<DebugWindow>,
+}
+
+impl DebugWindows {
+    pub fn new(video_subsystem: VideoSubsystem) -> DebugWindows {
+        DebugWindows {
+            video_subsystem,
+            windows: Vec::new(),
+        }
+    }
+
+    // closes the views if any of them is open, opens all of them otherwise
+    pub fn toggle(&mut self, views: &[DebugView], emulator: &Gameboy) -> Result<(), String> {
+        let before = self.windows.len();
+        self.windows.retain(|window| !views.contains(&window.view));
+        if self.windows.len() != before {
+            return Ok(());
+        }
+        for &view in views {
+            self.open(view, emulator)?;
+        }
+        self.update(emulator)
+    }
+
+    fn open(&mut self, view: DebugView, emulator: &Gameboy) -> Result<(), String> {
+        let image = view.render(emulator);
+        let window = self
+            .video_subsystem
+            .window(
+                view.get_title(),
+                image.width as u32 * WINDOW_SCALE,
+                image.height as u32 * WINDOW_SCALE,
+            )
+            .resizable()
+            .build()
+            .map_err(|e| format!("{:?}", e))?;
+        // no vsync, only the main window paces the emulation
+        let canvas = window
+            .into_canvas()
+            .build()
+            .map_err(|e| format!("{:?}", e))?;
+        let texture = canvas
+            .texture_creator()
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                image.width as u32,
+                image.height as u32,
+            )
+            .map_err(|e| format!("{:?}", e))?;
+        self.windows.push(DebugWindow {
+            view,
+            canvas,
+            texture,
+        });
+        Ok(())
+    }
+
+    // returns false if the window isn't one of the debug windows
+    pub fn close(&mut self, window_id: u32) -> bool {
+        let before = self.windows.len();
+        self.windows
+            .retain(|window| window.canvas.window().id() != window_id);
+        self.windows.len() != before
+    }
+
+    pub fn update(&mut self, emulator: &Gameboy) -> Result<(), String> {
+        for window in &mut self.windows {
+            let image = window.view.render(emulator);
+            window
+                .texture
+                .update(None, &image.pixels, image.width * 3)
+                .map_err(|e| format!("{:?}", e))?;
+            window.canvas.clear();
+            window.canvas.copy(&window.texture, None, None)?;
+            window.canvas.present();
+        }
+        Ok(())
+    }
+}
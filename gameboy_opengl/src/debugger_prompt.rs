@@ -0,0 +1,384 @@
+use gameboy_core::{
+    BreakReason, Breakpoint, CallFrame, CrashContext, Gameboy, Registers, SymbolTable, WatchKind,
+    Watchpoint,
+};
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+c                  continue
+s                  step into
+n                  step over
+f                  step out
+u [bank:]addr      run until the address is reached
+b [bank:]addr      add a breakpoint
+d [bank:]addr      delete a breakpoint
+w addr [r|w|rw]    add a watchpoint, rw by default
+dw addr [r|w|rw]   delete a watchpoint
+l                  list breakpoints and watchpoints
+r                  show the registers
+bt                 show the call stack
+set reg value      set a register, like set hl C000
+x addr [count]     show memory
+dis [addr] [count] disassemble, from pc by default
+dump bank file     disassemble a whole rom bank to an .asm file
+q                  quit
+an empty line repeats the last command, numbers are hex,
+labels from the symbol file can be used instead of addresses";
+
+pub enum PromptAction {
+    Continue,
+    // stdin was closed, nothing more can be typed at the prompt
+    Detach,
+    Quit,
+}
+
+// blocks on stdin until a command resumes the emulation
+pub fn run(emulator: &mut Gameboy) -> Result<PromptAction, String> {
+    print_break_reason(emulator);
+    print_registers(emulator.get_registers(), emulator.get_rom_bank());
+    print_disassembly(emulator, emulator.get_registers().pc, 1);
+
+    let stdin = io::stdin();
+    let mut last_command = String::new();
+    loop {
+        print!("(gbdb) ");
+        io::stdout().flush().map_err(|e| format!("{:?}", e))?;
+        let mut line = String::new();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| format!("{:?}", e))?
+            == 0
+        {
+            eprintln!("stdin is closed, resuming without the debugger");
+            return Ok(PromptAction::Detach);
+        }
+        let line = line.trim();
+        let command = if line.is_empty() {
+            last_command.clone()
+        } else {
+            line.to_string()
+        };
+        last_command = command.clone();
+
+        let arguments: Vec<&str> = command.split_whitespace().collect();
+        let result = match arguments.as_slice() {
+            [] => Ok(None),
+            ["c"] => {
+                emulator.resume();
+                Ok(Some(PromptAction::Continue))
+            }
+            ["s"] => {
+                emulator.step_into();
+                Ok(Some(PromptAction::Continue))
+            }
+            ["n"] => {
+                emulator.step_over();
+                Ok(Some(PromptAction::Continue))
+            }
+            ["f"] => {
+                emulator.step_out();
+                Ok(Some(PromptAction::Continue))
+            }
+            ["u", location] => parse_breakpoint(emulator, location).map(|breakpoint| {
+                emulator.run_to(breakpoint);
+                Some(PromptAction::Continue)
+            }),
+            ["b", location] => parse_breakpoint(emulator, location).map(|breakpoint| {
+                emulator.add_breakpoint(breakpoint);
+                None
+            }),
+            ["d", location] => parse_breakpoint(emulator, location).map(|breakpoint| {
+                emulator.remove_breakpoint(breakpoint);
+                None
+            }),
+            ["w", address, kind @ ..] => {
+                parse_watchpoint(emulator, address, kind).map(|watchpoint| {
+                    emulator.add_watchpoint(watchpoint);
+                    None
+                })
+            }
+            ["dw", address, kind @ ..] => {
+                parse_watchpoint(emulator, address, kind).map(|watchpoint| {
+                    emulator.remove_watchpoint(watchpoint);
+                    None
+                })
+            }
+            ["l"] => {
+                list_breakpoints(emulator);
+                Ok(None)
+            }
+            ["r"] => {
+                print_registers(emulator.get_registers(), emulator.get_rom_bank());
+                Ok(None)
+            }
+            ["bt"] if !emulator.is_tracking_call_stack() => Err(String::from(
+                "the call stack isn't followed, start with --debug",
+            )),
+            ["bt"] => {
+                let pc = emulator.get_registers().pc;
+                let call_stack = emulator.get_call_stack();
+                let symbols = emulator.get_symbols();
+                for line in format_call_stack(pc, emulator.get_rom_bank(), &call_stack, symbols) {
+                    println!("{}", line);
+                }
+                Ok(None)
+            }
+            ["set", register, value] => {
+                set_register(emulator.get_registers_mut(), register, value).map(|_| None)
+            }
+            ["x", address, count @ ..] => {
+                examine_memory(emulator, address, count.first().copied()).map(|_| None)
+            }
+            ["dis"] => {
+                print_disassembly(emulator, emulator.get_registers().pc, 8);
+                Ok(None)
+            }
+            ["dis", address, count @ ..] => {
+                disassemble(emulator, address, count.first().copied()).map(|_| None)
+            }
+            ["dump", bank, file_name] => dump_bank(emulator, bank, file_name).map(|_| None),
+            ["q"] => Ok(Some(PromptAction::Quit)),
+            ["h"] | ["help"] => {
+                println!("{}", HELP);
+                Ok(None)
+            }
+            _ => Err(format!(
+                "unknown command: {}, h shows the commands",
+                command
+            )),
+        };
+
+        match result {
+            Ok(Some(action)) => return Ok(action),
+            Ok(None) => (),
+            Err(message) => println!("{}", message),
+        }
+    }
+}
+
+// the panic message only has the address that was accessed,
+// this tells where the code that did it is
+pub fn print_crash_report(crash_context: &CrashContext, symbols: &SymbolTable) {
+    let pc = crash_context.get_pc();
+    let rom_bank = crash_context.get_rom_bank();
+    eprintln!(
+        "the emulator crashed at {:04X} {}",
+        pc,
+        symbols.format_address(rom_bank, pc)
+    );
+    let call_stack = crash_context.get_call_stack();
+    for line in format_call_stack(pc, rom_bank, &call_stack, symbols) {
+        eprintln!("{}", line);
+    }
+}
+
+// the innermost frame first, each frame shows where the call came from
+fn format_call_stack(
+    pc: u16,
+    rom_bank: usize,
+    call_stack: &[CallFrame],
+    symbols: &SymbolTable,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "#0 {:04X} {}",
+        pc,
+        symbols.format_address(rom_bank, pc)
+    )];
+    for (depth, frame) in call_stack.iter().rev().enumerate() {
+        let caller = symbols.format_address(frame.bank, frame.caller);
+        let kind = if frame.interrupt { " (interrupt)" } else { "" };
+        lines.push(format!(
+            "#{} {:04X} {}{}",
+            depth + 1,
+            frame.caller,
+            caller,
+            kind
+        ));
+    }
+    lines
+}
+
+fn print_break_reason(emulator: &Gameboy) {
+    match emulator.get_break_reason() {
+        Some(BreakReason::Breakpoint(breakpoint)) => println!(
+            "breakpoint at {} {}",
+            format_breakpoint(&breakpoint),
+            emulator.format_address(breakpoint.address)
+        ),
+        Some(BreakReason::Watchpoint { watchpoint, value }) => match value {
+            Some(value) => println!("wrote {:02X} to {:04X}", value, watchpoint.address),
+            None => println!("read from {:04X}", watchpoint.address),
+        },
+        Some(BreakReason::Step) | None => (),
+    }
+}
+
+fn print_registers(registers: &Registers, rom_bank: usize) {
+    println!(
+        "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:02X}:{:04X}",
+        registers.get_af(),
+        registers.get_bc(),
+        registers.get_de(),
+        registers.get_hl(),
+        registers.sp,
+        if (0x4000..0x8000).contains(&registers.pc) {
+            rom_bank
+        } else {
+            0
+        },
+        registers.pc
+    );
+}
+
+fn list_breakpoints(emulator: &Gameboy) {
+    for breakpoint in emulator.get_breakpoints() {
+        println!("breakpoint {}", format_breakpoint(breakpoint));
+    }
+    for watchpoint in emulator.get_watchpoints() {
+        let kind = match watchpoint.kind {
+            WatchKind::Read => "r",
+            WatchKind::Write => "w",
+            WatchKind::ReadWrite => "rw",
+        };
+        println!("watchpoint {:04X} {}", watchpoint.address, kind);
+    }
+}
+
+fn format_breakpoint(breakpoint: &Breakpoint) -> String {
+    match breakpoint.bank {
+        Some(bank) => format!("{:02X}:{:04X}", bank, breakpoint.address),
+        None => format!("{:04X}", breakpoint.address),
+    }
+}
+
+fn examine_memory(emulator: &Gameboy, address: &str, count: Option<&str>) -> Result<(), String> {
+    let address = parse_address(emulator, address)?;
+    let count = match count {
+        Some(count) => parse_hex(count)?,
+        None => 0x10,
+    };
+    // u32, so the last row of x 0 FFFF doesn't overflow
+    let count = u32::from(count);
+    for row_start in (0..count).step_by(0x10) {
+        let row_address = address.wrapping_add(row_start as u16);
+        let bytes: Vec<String> = (row_start..std::cmp::min(row_start + 0x10, count))
+            .map(|offset| {
+                let value = emulator.read_byte(address.wrapping_add(offset as u16));
+                format!("{:02X}", value)
+            })
+            .collect();
+        println!("{:04X}: {}", row_address, bytes.join(" "));
+    }
+    Ok(())
+}
+
+fn disassemble(emulator: &Gameboy, address: &str, count: Option<&str>) -> Result<(), String> {
+    let address = parse_address(emulator, address)?;
+    let count = match count {
+        Some(count) => parse_hex(count)?,
+        None => 8,
+    };
+    print_disassembly(emulator, address, count);
+    Ok(())
+}
+
+fn print_disassembly(emulator: &Gameboy, address: u16, count: u16) {
+    let mut address = address;
+    let rom_bank = emulator.get_rom_bank();
+    for _ in 0..count {
+        if let Some(label) = emulator.get_symbols().get_label(rom_bank, address) {
+            println!("{}:", label);
+        }
+        let instruction = emulator.disassemble(address);
+        let bytes: Vec<String> = (0..instruction.length)
+            .map(|offset| format!("{:02X}", emulator.read_byte(address.wrapping_add(offset))))
+            .collect();
+        println!(
+            "{:04X}: {:<9} {}",
+            address,
+            bytes.join(" "),
+            instruction.text
+        );
+        address = address.wrapping_add(instruction.length);
+    }
+}
+
+fn dump_bank(emulator: &Gameboy, bank: &str, file_name: &str) -> Result<(), String> {
+    let bank = usize::from(parse_hex(bank)?);
+    let disassembly = emulator.disassemble_bank(bank)?;
+    fs::write(file_name, disassembly).map_err(|e| format!("{:?}", e))?;
+    println!("wrote bank {:02X} to {}", bank, file_name);
+    Ok(())
+}
+
+fn set_register(registers: &mut Registers, register: &str, value: &str) -> Result<(), String> {
+    let value = parse_hex(value)?;
+    let byte = value as u8;
+    match register.to_lowercase().as_str() {
+        "a" => registers.a = byte,
+        "b" => registers.b = byte,
+        "c" => registers.c = byte,
+        "d" => registers.d = byte,
+        "e" => registers.e = byte,
+        "h" => registers.h = byte,
+        "l" => registers.l = byte,
+        "af" => registers.set_af(value),
+        "bc" => registers.set_bc(value),
+        "de" => registers.set_de(value),
+        "hl" => registers.set_hl(value),
+        "sp" => registers.sp = value,
+        "pc" => registers.pc = value,
+        _ => return Err(format!("unknown register: {}", register)),
+    }
+    Ok(())
+}
+
+// a label stops in the bank it's in, when it's in the switchable rom area
+fn parse_breakpoint(emulator: &Gameboy, location: &str) -> Result<Breakpoint, String> {
+    if let Some(symbol) = emulator.get_symbols().find(location) {
+        let bank = match symbol.address {
+            0x4000..=0x7FFF => Some(symbol.bank),
+            _ => None,
+        };
+        return Ok(Breakpoint::new(bank, symbol.address));
+    }
+    match location.find(':') {
+        Some(index) => {
+            let bank = usize::from(parse_hex(&location[..index])?);
+            let address = parse_hex(&location[index + 1..])?;
+            Ok(Breakpoint::new(Some(bank), address))
+        }
+        None => Ok(Breakpoint::new(None, parse_hex(location)?)),
+    }
+}
+
+fn parse_watchpoint(
+    emulator: &Gameboy,
+    address: &str,
+    kind: &[&str],
+) -> Result<Watchpoint, String> {
+    let kind = match kind {
+        [] | ["rw"] => WatchKind::ReadWrite,
+        ["r"] => WatchKind::Read,
+        ["w"] => WatchKind::Write,
+        _ => return Err(format!("unknown watchpoint kind: {}", kind.join(" "))),
+    };
+    Ok(Watchpoint::new(parse_address(emulator, address)?, kind))
+}
+
+fn parse_address(emulator: &Gameboy, value: &str) -> Result<u16, String> {
+    match emulator.get_symbols().find(value) {
+        Some(symbol) => Ok(symbol.address),
+        None => parse_hex(value),
+    }
+}
+
+fn parse_hex(value: &str) -> Result<u16, String> {
+    let digits = value
+        .trim_start_matches('$')
+        .trim_start_matches("0x")
+        .trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("not a hex number: {}", value))
+}
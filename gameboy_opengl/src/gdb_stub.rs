@@ -0,0 +1,352 @@
+use gameboy_core::{BreakReason, Breakpoint, Gameboy, WatchKind, Watchpoint};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+// the registers are sent as AF, BC, DE, HL, SP and PC, 16 bits each, little endian
+const REGISTER_COUNT: usize = 6;
+const INTERRUPT: u8 = 0x03;
+// the largest packet gdb is told it may send, or may ask to be sent back
+const PACKET_SIZE: usize = 0x1000;
+
+pub enum GdbAction {
+    Continue,
+    Detach,
+    Quit,
+}
+
+pub struct GdbStub {
+    stream: TcpStream,
+    // gdb waits for a stop reply after it continues or steps
+    waiting_for_stop: bool,
+}
+
+impl GdbStub {
+    // waits for gdb to attach, the emulation doesn't start before that
+    pub fn listen(port: u16) -> io::Result<GdbStub> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("waiting for gdb on port {}", port);
+        let (stream, address) = listener.accept()?;
+        println!("gdb attached from {}", address);
+        stream.set_nodelay(true)?;
+        Ok(GdbStub {
+            stream,
+            waiting_for_stop: false,
+        })
+    }
+
+    // gdb sends a single 0x03 byte to stop the emulation while it's running
+    pub fn poll_interrupt(&mut self, emulator: &mut Gameboy) -> io::Result<GdbAction> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0; 1];
+        let result = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Ok(GdbAction::Detach),
+            Ok(_) => {
+                if byte[0] == INTERRUPT {
+                    emulator.request_break();
+                }
+                Ok(GdbAction::Continue)
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => Ok(GdbAction::Continue),
+            Err(ref e) if is_disconnect(e) => Ok(GdbAction::Detach),
+            Err(e) => Err(e),
+        }
+    }
+
+    // called whenever the emulation stops, serves gdb's requests until it resumes it,
+    // losing the connection detaches gdb instead of stopping the emulator
+    pub fn handle_stop(&mut self, emulator: &mut Gameboy) -> GdbAction {
+        match self.serve(emulator) {
+            Ok(action) => action,
+            Err(e) => {
+                if !is_disconnect(&e) {
+                    eprintln!("lost the connection to gdb: {}", e);
+                }
+                emulator.resume();
+                GdbAction::Detach
+            }
+        }
+    }
+
+    fn serve(&mut self, emulator: &mut Gameboy) -> io::Result<GdbAction> {
+        if self.waiting_for_stop {
+            self.waiting_for_stop = false;
+            let reply = GdbStub::stop_reply(emulator);
+            self.send_packet(&reply)?;
+        }
+
+        loop {
+            let packet = self.read_packet()?;
+            let (command, arguments) = packet.split_at(std::cmp::min(1, packet.len()));
+            let reply = match command {
+                "?" => GdbStub::stop_reply(emulator),
+                "g" => GdbStub::read_registers(emulator),
+                "G" => GdbStub::write_registers(emulator, arguments),
+                "p" => GdbStub::read_register(emulator, arguments),
+                "P" => GdbStub::write_register(emulator, arguments),
+                "m" => GdbStub::read_memory(emulator, arguments),
+                "M" => GdbStub::write_memory(emulator, arguments),
+                "Z" | "z" => GdbStub::set_breakpoint(emulator, command == "Z", arguments),
+                "c" | "s" => {
+                    if let Some(address) = parse_hex(arguments) {
+                        emulator.get_registers_mut().pc = address as u16;
+                    }
+                    if command == "c" {
+                        emulator.resume();
+                    } else {
+                        emulator.step_into();
+                    }
+                    self.waiting_for_stop = true;
+                    return Ok(GdbAction::Continue);
+                }
+                "D" => {
+                    self.send_packet("OK")?;
+                    emulator.resume();
+                    return Ok(GdbAction::Detach);
+                }
+                "k" => return Ok(GdbAction::Quit),
+                "H" => String::from("OK"),
+                "q" if arguments.starts_with("Supported") => {
+                    format!("PacketSize={:x};swbreak+;hwbreak+", PACKET_SIZE)
+                }
+                "q" if arguments == "Attached" => String::from("1"),
+                "q" if arguments == "C" => String::from("QC1"),
+                "q" if arguments.starts_with("fThreadInfo") => String::from("m1"),
+                "q" if arguments.starts_with("sThreadInfo") => String::from("l"),
+                // an empty reply tells gdb the packet isn't supported
+                _ => String::new(),
+            };
+            self.send_packet(&reply)?;
+        }
+    }
+
+    fn stop_reply(emulator: &Gameboy) -> String {
+        match emulator.get_break_reason() {
+            Some(BreakReason::Watchpoint { watchpoint, .. }) => {
+                let kind = match watchpoint.kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::ReadWrite => "awatch",
+                };
+                format!("T05{}:{:04x};", kind, watchpoint.address)
+            }
+            Some(BreakReason::Breakpoint(_)) => String::from("T05swbreak:;"),
+            _ => String::from("S05"),
+        }
+    }
+
+    fn get_registers(emulator: &Gameboy) -> [u16; REGISTER_COUNT] {
+        let registers = emulator.get_registers();
+        [
+            registers.get_af(),
+            registers.get_bc(),
+            registers.get_de(),
+            registers.get_hl(),
+            registers.sp,
+            registers.pc,
+        ]
+    }
+
+    fn set_register(emulator: &mut Gameboy, index: usize, value: u16) -> bool {
+        let registers = emulator.get_registers_mut();
+        match index {
+            0 => registers.set_af(value),
+            1 => registers.set_bc(value),
+            2 => registers.set_de(value),
+            3 => registers.set_hl(value),
+            4 => registers.sp = value,
+            5 => registers.pc = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn read_registers(emulator: &Gameboy) -> String {
+        GdbStub::get_registers(emulator)
+            .iter()
+            .map(|&value| format!("{:02x}{:02x}", value as u8, value >> 8))
+            .collect()
+    }
+
+    fn write_registers(emulator: &mut Gameboy, arguments: &str) -> String {
+        let bytes = match decode_hex_bytes(arguments) {
+            Some(ref bytes) if bytes.len() >= REGISTER_COUNT * 2 => bytes.clone(),
+            _ => return String::from("E01"),
+        };
+        for index in 0..REGISTER_COUNT {
+            let value = u16::from(bytes[index * 2]) | (u16::from(bytes[index * 2 + 1]) << 8);
+            GdbStub::set_register(emulator, index, value);
+        }
+        String::from("OK")
+    }
+
+    fn read_register(emulator: &Gameboy, arguments: &str) -> String {
+        match parse_hex(arguments) {
+            Some(index) if index < REGISTER_COUNT => {
+                let value = GdbStub::get_registers(emulator)[index];
+                format!("{:02x}{:02x}", value as u8, value >> 8)
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn write_register(emulator: &mut Gameboy, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, '=');
+        let index = parts.next().and_then(parse_hex);
+        let bytes = parts.next().and_then(decode_hex_bytes);
+        match (index, bytes) {
+            (Some(index), Some(ref bytes)) if bytes.len() == 2 => {
+                let value = u16::from(bytes[0]) | (u16::from(bytes[1]) << 8);
+                if GdbStub::set_register(emulator, index, value) {
+                    String::from("OK")
+                } else {
+                    String::from("E01")
+                }
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn read_memory(emulator: &Gameboy, arguments: &str) -> String {
+        match parse_address_and_length(arguments) {
+            // each byte takes two hex digits in the reply
+            Some((address, length)) if length <= PACKET_SIZE / 2 => (0..length)
+                .map(|offset| {
+                    let address = (address + offset) as u16;
+                    format!("{:02x}", emulator.read_byte(address))
+                })
+                .collect(),
+            _ => String::from("E01"),
+        }
+    }
+
+    fn write_memory(emulator: &mut Gameboy, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, ':');
+        let location = parts.next().and_then(parse_address_and_length);
+        let bytes = parts.next().and_then(decode_hex_bytes);
+        match (location, bytes) {
+            (Some((address, length)), Some(bytes)) if bytes.len() == length => {
+                for (offset, &value) in bytes.iter().enumerate() {
+                    emulator.write_byte((address + offset) as u16, value);
+                }
+                String::from("OK")
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    // software and hardware breakpoints work the same way in the emulator
+    fn set_breakpoint(emulator: &mut Gameboy, insert: bool, arguments: &str) -> String {
+        let parts: Vec<&str> = arguments.split(',').collect();
+        let (kind, address) = match parts.as_slice() {
+            [kind, address, ..] => match parse_hex(address) {
+                Some(address) => (*kind, address as u16),
+                None => return String::from("E01"),
+            },
+            _ => return String::from("E01"),
+        };
+        let watch_kind = match kind {
+            "0" | "1" => {
+                let breakpoint = Breakpoint::new(None, address);
+                if insert {
+                    emulator.add_breakpoint(breakpoint);
+                } else {
+                    emulator.remove_breakpoint(breakpoint);
+                }
+                return String::from("OK");
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::ReadWrite,
+            _ => return String::new(),
+        };
+        let watchpoint = Watchpoint::new(address, watch_kind);
+        if insert {
+            emulator.add_watchpoint(watchpoint);
+        } else {
+            emulator.remove_watchpoint(watchpoint);
+        }
+        String::from("OK")
+    }
+
+    // packets look like $data#checksum, and are acknowledged with a +
+    fn read_packet(&mut self) -> io::Result<String> {
+        loop {
+            let mut byte = [0; 1];
+            self.read_byte(&mut byte)?;
+            if byte[0] != b'$' {
+                // acknowledgements and stray interrupts while stopped
+                continue;
+            }
+            let mut data = Vec::new();
+            loop {
+                self.read_byte(&mut byte)?;
+                if byte[0] == b'#' {
+                    break;
+                }
+                data.push(byte[0]);
+            }
+            let mut checksum = [0; 2];
+            self.read_byte(&mut checksum[..1])?;
+            self.read_byte(&mut checksum[1..])?;
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+            if expected == Some(get_checksum(&data)) {
+                self.stream.write_all(b"+")?;
+                return Ok(String::from_utf8_lossy(&data).into_owned());
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn read_byte(&mut self, byte: &mut [u8]) -> io::Result<()> {
+        if self.stream.read(byte)? == 0 {
+            return Err(io::Error::new(
+                ErrorKind::ConnectionAborted,
+                "gdb disconnected",
+            ));
+        }
+        Ok(())
+    }
+
+    fn send_packet(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, get_checksum(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())?;
+        self.stream.flush()
+    }
+}
+
+fn is_disconnect(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::BrokenPipe
+    )
+}
+
+fn get_checksum(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0, |checksum: u8, &byte| checksum.wrapping_add(byte))
+}
+
+fn parse_hex(value: &str) -> Option<usize> {
+    usize::from_str_radix(value, 16).ok()
+}
+
+fn parse_address_and_length(value: &str) -> Option<(usize, usize)> {
+    let mut parts = value.splitn(2, ',');
+    let address = parse_hex(parts.next()?)?;
+    let length = parse_hex(parts.next()?)?;
+    Some((address & 0xFFFF, length))
+}
+
+fn decode_hex_bytes(value: &str) -> Option<Vec<u8>> {
+    if value.len() & 0x01 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -1,22 +1,91 @@
+mod debug_windows;
+mod debugger_prompt;
+mod gdb_stub;
+mod native_accelerometer;
 mod native_rtc;
+mod native_rumble;
 mod screen;
+mod still_image;
+mod video_recorder;
 
+use crate::debug_windows::{DebugView, DebugWindows};
+use crate::debugger_prompt::PromptAction;
+use crate::gdb_stub::{GdbAction, GdbStub};
+use crate::native_accelerometer::NativeAccelerometer;
 use crate::native_rtc::NativeRTC;
+use crate::native_rumble::{MotorState, NativeRumble};
 use crate::screen::Screen;
+use crate::still_image::StillImage;
+pub use crate::video_recorder::VideoFormat;
+use crate::video_recorder::VideoRecorder;
 use directories::BaseDirs;
-use gameboy_core::{Button, Cartridge, Gameboy, Rtc, StepResult};
+use gameboy_core::{
+    parse_cheat_file, Button, Cartridge, CrashContext, Gameboy, Rtc, StepResult, SymbolTable,
+    TestRomResult, TraceFilter, TraceHandle, Tracer,
+};
 use sdl2::audio::AudioSpecDesired;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::haptic::Haptic;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::{Color, PixelFormatEnum};
-use std::cell::RefCell;
-use std::fs::{self, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use sdl2::{JoystickSubsystem, Sdl};
+use std::cell::{Cell, RefCell};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub fn start(rom: Vec<u8>) -> Result<(), String> {
+thread_local! {
+    // panic hooks have to be Send, so they can't hold on to the emulator's
+    // crash context, it's read from here when the emulator's thread panics
+    static CRASH_REPORT: RefCell<Option<(Rc<CrashContext>, SymbolTable)>> = const { RefCell::new(None) };
+}
+
+// the whole dmg_sound and cgb_sound roms take about a minute
+const TEST_ROM_TIMEOUT_FRAMES: u32 = 60 * 60 * 3;
+
+pub struct Options {
+    pub video_format: VideoFormat,
+    pub camera_image: Option<PathBuf>,
+    // stop in the debugger before the first instruction
+    pub debug: bool,
+    // port a gdb stub listens on, gdb attaches before the emulation starts
+    pub gdb_port: Option<u16>,
+    // every instruction is logged there in the gameboy-doctor format
+    pub trace_file: Option<PathBuf>,
+    // keeps only the last instructions, they're written to the trace file,
+    // or stderr, when the emulator stops or crashes
+    pub trace_last: Option<usize>,
+    pub trace_filters: Vec<TraceFilter>,
+    // labels get a line of their own in the trace
+    pub trace_labels: bool,
+    // RGBDS or no$gmb labels, for the debugger and traces
+    pub symbol_file: Option<PathBuf>,
+    // follow the call stack, for a report when the emulator crashes,
+    // the debugger and gdb always follow it
+    pub crash_report: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            video_format: VideoFormat::Y4m,
+            camera_image: None,
+            debug: false,
+            gdb_port: None,
+            trace_file: None,
+            trace_last: None,
+            trace_filters: Vec::new(),
+            trace_labels: false,
+            symbol_file: None,
+            crash_report: false,
+        }
+    }
+}
+
+pub fn start(rom: Vec<u8>, options: Options) -> Result<(), String> {
     let sdl_context = sdl2::init()?;
 
     let audio_subsystem = sdl_context.audio()?;
@@ -38,6 +107,7 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
         .build()
         .map_err(|e| format!("{:?}", e))?;
 
+    let mut debug_windows = DebugWindows::new(video_subsystem.clone());
     let mut canvas = window
         .into_canvas()
         .present_vsync()
@@ -53,9 +123,12 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
 
     let rtc = Box::new(NativeRTC::new());
     let mut emulator = Gameboy::from_rom(rom, rtc)?;
+    // SDL might not give us the exact rate we asked for
+    emulator.set_audio_sample_rate(device.spec().freq as u32);
 
     load_ram_save_data(emulator.get_cartridge_mut()).map_err(|e| format!("{:?}", e))?;
     load_timestamp_data(emulator.get_cartridge_mut()).map_err(|e| format!("{:?}", e))?;
+    load_cheats(&mut emulator)?;
 
     let mut ram_save_file = get_ram_save_file(emulator.get_cartridge());
     let mut timestamp_save_file = get_timestamp_save_file(emulator.get_cartridge());
@@ -67,12 +140,95 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
             *ram_changed.borrow_mut() = true;
         }));
     }
+    let motor_state = Rc::new(RefCell::new(MotorState::new()));
+    let mut rumble_device = if emulator.get_cartridge().has_rumble() {
+        open_rumble_device(&sdl_context)
+    } else {
+        None
+    };
+    if rumble_device.is_some() {
+        emulator.set_rumble(Box::new(NativeRumble::new(motor_state.clone())));
+    }
+
+    let tilt = Rc::new(Cell::new((0.0, 0.0)));
+    if emulator.get_cartridge().has_accelerometer() {
+        emulator.set_accelerometer(Box::new(NativeAccelerometer::new(tilt.clone())));
+    }
+
+    if let Some(ref camera_image) = options.camera_image {
+        if emulator.get_cartridge().has_camera() {
+            emulator.set_image_source(Box::new(StillImage::from_png(camera_image)?));
+        }
+    }
+
+    let mut gdb_stub = match options.gdb_port {
+        Some(port) => Some(GdbStub::listen(port).map_err(|e| format!("{:?}", e))?),
+        None => None,
+    };
+    if options.debug || gdb_stub.is_some() {
+        emulator.request_break();
+    }
+    // stdin was closed, so F7 and breakpoints can't stop in the prompt anymore
+    let mut prompt_detached = false;
+
+    let tracer = match (options.trace_last, &options.trace_file) {
+        (Some(count), _) => Some(Tracer::with_ring_buffer(count)),
+        (None, Some(trace_file)) => {
+            let file = File::create(trace_file).map_err(|e| format!("{:?}", e))?;
+            Some(Tracer::new(Box::new(BufWriter::new(file))))
+        }
+        (None, None) => None,
+    };
+    if let Some(ref symbol_file) = options.symbol_file {
+        let contents = fs::read_to_string(symbol_file).map_err(|e| format!("{:?}", e))?;
+        let symbols = gameboy_core::parse_symbol_file(&contents)
+            .map_err(|e| format!("{}: {}", symbol_file.display(), e))?;
+        emulator.set_symbols(symbols);
+    }
+    if options.debug || options.gdb_port.is_some() || options.crash_report {
+        emulator.set_call_stack_tracking(true);
+        set_crash_report_panic_hook(emulator.get_crash_context(), emulator.get_symbols().clone());
+    }
+
+    if let Some(mut tracer) = tracer {
+        tracer.set_show_labels(options.trace_labels);
+        for &filter in &options.trace_filters {
+            tracer.add_filter(filter);
+        }
+        set_trace_panic_hook(
+            tracer.get_handle(),
+            options.trace_last.is_some(),
+            options.trace_file.clone(),
+        );
+        emulator.start_trace(tracer)?;
+    }
+
     let mut screen = Screen::new();
+    let mut video_recorder: Option<VideoRecorder> = None;
 
+    let mut frame_count = emulator.get_frame_count();
     let mut event_pump = sdl_context.event_pump()?;
     'game_loop: loop {
         loop {
             let step_result = emulator.emulate(&mut screen);
+            // StepResult::VBlank can be hidden behind AudioBufferFull
+            let frame_finished = emulator.get_frame_count() != frame_count;
+            frame_count = emulator.get_frame_count();
+            if let Some(ref mut recorder) = video_recorder {
+                let written = if frame_finished {
+                    recorder.frame_finished(screen.get_frame_buffer(), emulator.get_cycle_count())
+                } else {
+                    Ok(())
+                }
+                .and_then(|_| recorder.update(emulator.get_cycle_count()));
+                if let Err(e) = written {
+                    eprintln!("couldn't write the video recording, stopping it: {:?}", e);
+                    video_recorder = None;
+                    if let Err(e) = emulator.stop_audio_recording() {
+                        eprintln!("couldn't save the audio recording: {}", e);
+                    }
+                }
+            }
             match step_result {
                 StepResult::VBlank => {
                     let frame_buffer = screen.get_frame_buffer();
@@ -80,6 +236,17 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
                     canvas.clear();
                     canvas.copy(&texture, None, None)?;
                     canvas.present();
+                    debug_windows.update(&emulator)?;
+                    if let Some((_, ref mut haptic)) = rumble_device {
+                        let strength = motor_state
+                            .borrow_mut()
+                            .take_strength(emulator.get_cycle_count());
+                        if strength > 0.0 {
+                            haptic.rumble_play(strength, 100);
+                        } else {
+                            haptic.rumble_stop();
+                        }
+                    }
                     std::thread::sleep(Duration::from_millis(10));
                     break;
                 }
@@ -91,10 +258,34 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
                     device.queue(audio_buffer);
                     break;
                 }
+                StepResult::Breakpoint => match gdb_stub {
+                    Some(ref mut stub) => match stub.handle_stop(&mut emulator) {
+                        GdbAction::Continue => (),
+                        GdbAction::Detach => gdb_stub = None,
+                        GdbAction::Quit => break 'game_loop,
+                    },
+                    None => match debugger_prompt::run(&mut emulator)? {
+                        PromptAction::Continue => (),
+                        PromptAction::Detach => {
+                            prompt_detached = true;
+                            remove_breakpoints(&mut emulator);
+                        }
+                        PromptAction::Quit => break 'game_loop,
+                    },
+                },
                 StepResult::Nothing => (),
             }
         }
 
+        if let Some(ref mut stub) = gdb_stub {
+            if let GdbAction::Detach = stub
+                .poll_interrupt(&mut emulator)
+                .map_err(|e| format!("{:?}", e))?
+            {
+                gdb_stub = None;
+            }
+        }
+
         if *ram_changed.borrow() && emulator.get_cartridge().has_battery() {
             if let Some(ref mut ram_save_file) = ram_save_file {
                 save_ram_data(emulator.get_cartridge(), ram_save_file)
@@ -113,6 +304,112 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => break 'game_loop,
+                // with debug windows open, closing the main window doesn't quit by itself,
+                // closing a debug window only closes that window
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } if !debug_windows.close(window_id) => break 'game_loop,
+                Event::MouseMotion { x, y, .. } => {
+                    let (width, height) = canvas.window().size();
+                    tilt.set(native_accelerometer::mouse_position_to_tilt(
+                        x, y, width, height,
+                    ));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => {
+                    // F6 records the audio along with the video, there's only one wav recorder
+                    if video_recorder.is_some() {
+                        eprintln!("stop the video recording with F6 first");
+                    } else if emulator.is_recording_audio() {
+                        if let Err(e) = emulator.stop_audio_recording() {
+                            eprintln!("couldn't save the audio recording: {}", e);
+                        }
+                    } else if let Err(e) = start_audio_recording(&mut emulator) {
+                        // a full disk shouldn't end the game
+                        eprintln!("couldn't start the audio recording: {}", e);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(recorder) = video_recorder.take() {
+                        if let Err(e) = recorder.finish() {
+                            eprintln!("couldn't save the video recording: {:?}", e);
+                        }
+                        if let Err(e) = emulator.stop_audio_recording() {
+                            eprintln!("couldn't save the audio recording: {}", e);
+                        }
+                    } else if emulator.is_recording_audio() {
+                        eprintln!("stop the audio recording with F5 first");
+                    } else {
+                        // the video and its audio share a name, so they're easy to pair up
+                        let recorder = start_audio_recording(&mut emulator).and_then(|path| {
+                            VideoRecorder::new(
+                                options.video_format,
+                                &path,
+                                screen.get_frame_buffer(),
+                                emulator.get_cycle_count(),
+                            )
+                            .map_err(|e| format!("{:?}", e))
+                        });
+                        match recorder {
+                            Ok(recorder) => video_recorder = Some(recorder),
+                            Err(e) => {
+                                eprintln!("couldn't start the video recording: {}", e);
+                                // don't leave the audio half of the recording running
+                                if let Err(e) = emulator.stop_audio_recording() {
+                                    eprintln!("couldn't save the audio recording: {}", e);
+                                }
+                            }
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    repeat: false,
+                    ..
+                } if gdb_stub.is_some() || !prompt_detached => emulator.request_break(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F8),
+                    repeat: false,
+                    ..
+                } => debug_windows.toggle(&[DebugView::TileData], &emulator)?,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => debug_windows
+                    .toggle(&[DebugView::TileMap(0), DebugView::TileMap(1)], &emulator)?,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    repeat: false,
+                    ..
+                } => debug_windows.toggle(&[DebugView::Sprites], &emulator)?,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => debug_windows.toggle(&[DebugView::Palettes], &emulator)?,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => {
+                    let saved = emulator.get_screenshot_png().and_then(|screenshot| {
+                        save_screenshot(emulator.get_cartridge(), &screenshot)
+                            .map_err(|e| format!("{:?}", e))
+                    });
+                    if let Err(e) = saved {
+                        eprintln!("couldn't save the screenshot: {}", e);
+                    }
+                }
                 Event::KeyDown {
                     keycode: Some(keycode),
                     ..
@@ -134,9 +431,116 @@ pub fn start(rom: Vec<u8>) -> Result<(), String> {
         }
     }
 
+    // a recording that can't be saved shouldn't cost the trace history
+    if let Some(recorder) = video_recorder {
+        if let Err(e) = recorder.finish() {
+            eprintln!("couldn't save the video recording: {:?}", e);
+        }
+    }
+    if let Err(e) = emulator.stop_audio_recording() {
+        eprintln!("couldn't save the audio recording: {}", e);
+    }
+    let trace_history = emulator.stop_trace()?;
+    // the emulator is gone, later panics shouldn't report on it
+    let _ = panic::take_hook();
+    CRASH_REPORT.with(|crash_report| crash_report.borrow_mut().take());
+    if options.trace_last.is_some() {
+        save_trace_history(&trace_history, options.trace_file.as_ref())
+            .map_err(|e| format!("{:?}", e))?;
+    }
+
     Ok(())
 }
 
+fn remove_breakpoints(emulator: &mut Gameboy) {
+    for breakpoint in emulator.get_breakpoints().to_vec() {
+        emulator.remove_breakpoint(breakpoint);
+    }
+    for watchpoint in emulator.get_watchpoints().to_vec() {
+        emulator.remove_watchpoint(watchpoint);
+    }
+}
+
+// runs a blargg test rom without a window or sound, as fast as possible
+pub fn run_test_rom(rom: Vec<u8>) -> Result<TestRomResult, String> {
+    let rtc = Box::new(NativeRTC::new());
+    let mut emulator = Gameboy::from_rom(rom, rtc)?;
+    let mut screen = Screen::new();
+    for _ in 0..TEST_ROM_TIMEOUT_FRAMES {
+        while emulator.emulate(&mut screen) != StepResult::VBlank {}
+        if let Some(result) = emulator.get_test_rom_result() {
+            return Ok(result);
+        }
+    }
+    Err(String::from("the test rom didn't finish"))
+}
+
+// the panic messages only have the address that was accessed, this tells
+// where the code that did it is, release builds abort right after the hook
+fn set_crash_report_panic_hook(crash_context: Rc<CrashContext>, symbols: SymbolTable) {
+    CRASH_REPORT.with(|crash_report| *crash_report.borrow_mut() = Some((crash_context, symbols)));
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // only the emulator's thread has a crash context
+        let _ = CRASH_REPORT.try_with(|crash_report| {
+            if let Ok(crash_report) = crash_report.try_borrow() {
+                if let Some((ref crash_context, ref symbols)) = *crash_report {
+                    debugger_prompt::print_crash_report(crash_context, symbols);
+                }
+            }
+        });
+    }));
+}
+
+// release builds abort on panics, so the lines before a crash
+// have to be saved from the hook, before the process goes away
+fn set_trace_panic_hook(trace_handle: TraceHandle, ring_buffer: bool, trace_file: Option<PathBuf>) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let result = if ring_buffer {
+            save_trace_history(&trace_handle.get_history(), trace_file.as_ref())
+        } else {
+            trace_handle.flush()
+        };
+        if let Err(e) = result {
+            eprintln!("couldn't save the trace: {:?}", e);
+        }
+    }));
+}
+
+fn save_trace_history(
+    trace_history: &[String],
+    trace_file: Option<&PathBuf>,
+) -> std::io::Result<()> {
+    match trace_file {
+        Some(trace_file) => {
+            let mut file = BufWriter::new(File::create(trace_file)?);
+            for line in trace_history {
+                writeln!(file, "{}", line)?;
+            }
+            file.flush()
+        }
+        None => {
+            for line in trace_history {
+                eprintln!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+// the first joystick that can rumble, the joystick subsystem
+// has to stay alive for as long as the haptic device is used
+fn open_rumble_device(sdl_context: &Sdl) -> Option<(JoystickSubsystem, Haptic)> {
+    let joystick_subsystem = sdl_context.joystick().ok()?;
+    let haptic_subsystem = sdl_context.haptic().ok()?;
+    let joysticks = joystick_subsystem.num_joysticks().ok()?;
+    let haptic = (0..joysticks).find_map(|id| haptic_subsystem.open_from_joystick_id(id).ok())?;
+    Some((joystick_subsystem, haptic))
+}
+
 fn keycode_to_button(keycode: Keycode) -> Option<Button> {
     match keycode {
         Keycode::Z => Some(Button::A),
@@ -160,6 +564,100 @@ fn get_ram_saves_path() -> Option<PathBuf> {
     Some(path_buf)
 }
 
+fn get_cheats_path() -> Option<PathBuf> {
+    let base_dir = BaseDirs::new()?;
+    let path_buf = base_dir
+        .config_dir()
+        .join("gameboy_emulator")
+        .join("cheats");
+    Some(path_buf)
+}
+
+// every game can have a cheat file, named after it, like the ram saves
+fn load_cheats(emulator: &mut Gameboy) -> Result<(), String> {
+    if let Some(cheats_dir) = get_cheats_path() {
+        let cheat_file = cheats_dir.join(format!("{}.cht", emulator.get_cartridge().get_name()));
+        if cheat_file.exists() {
+            let contents = fs::read_to_string(&cheat_file).map_err(|e| format!("{:?}", e))?;
+            let cheats = parse_cheat_file(&contents)
+                .map_err(|e| format!("{}: {}", cheat_file.display(), e))?;
+            for cheat in cheats {
+                emulator.add_cheat(cheat);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_recordings_path() -> Option<PathBuf> {
+    let base_dir = BaseDirs::new()?;
+    let path_buf = base_dir
+        .config_dir()
+        .join("gameboy_emulator")
+        .join("recordings");
+    Some(path_buf)
+}
+
+// recordings are named after the game and the time they were started,
+// the returned path has no extension yet
+fn new_recording_path(cartridge: &Cartridge) -> std::io::Result<PathBuf> {
+    let recordings_path = get_recordings_path()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "no config directory available"))?;
+    if !recordings_path.exists() {
+        fs::create_dir_all(&recordings_path)?;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    Ok(recordings_path.join(format!("{}-{}", cartridge.get_name(), timestamp)))
+}
+
+// returns the recording's path without an extension
+fn start_audio_recording(emulator: &mut Gameboy) -> Result<PathBuf, String> {
+    let recording_path =
+        new_recording_path(emulator.get_cartridge()).map_err(|e| format!("{:?}", e))?;
+    let recording_file =
+        create_recording_file(&recording_path, "wav").map_err(|e| format!("{:?}", e))?;
+    emulator.start_audio_recording(recording_file)?;
+    Ok(recording_path)
+}
+
+fn create_recording_file(
+    recording_path: &Path,
+    extension: &str,
+) -> std::io::Result<BufWriter<File>> {
+    let mut file_name = recording_path.as_os_str().to_owned();
+    file_name.push(".");
+    file_name.push(extension);
+    Ok(BufWriter::new(File::create(file_name)?))
+}
+
+fn get_screenshots_path() -> Option<PathBuf> {
+    let base_dir = BaseDirs::new()?;
+    let path_buf = base_dir
+        .config_dir()
+        .join("gameboy_emulator")
+        .join("screenshots");
+    Some(path_buf)
+}
+
+// screenshots can be taken several times a second, so they're named down to the millisecond
+fn save_screenshot(cartridge: &Cartridge, screenshot: &[u8]) -> std::io::Result<()> {
+    let screenshots_path = get_screenshots_path()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "no config directory available"))?;
+    if !screenshots_path.exists() {
+        fs::create_dir_all(&screenshots_path)?;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let screenshot_file_path =
+        screenshots_path.join(format!("{}-{}.png", cartridge.get_name(), timestamp));
+    fs::write(screenshot_file_path, screenshot)
+}
+
 fn load_ram_save_data(cartridge: &mut Cartridge) -> std::io::Result<()> {
     if cartridge.has_battery() {
         if let Some(ram_saves_dir) = get_ram_saves_path() {
@@ -194,6 +692,10 @@ fn load_timestamp_data(cartridge: &mut Cartridge) -> std::io::Result<()> {
                 let last_rtc = Rtc::from_bytes(&rtc_data);
                 let last_timestamp = u64::from_ne_bytes(timestamp_data);
                 cartridge.set_last_timestamp(last_rtc, last_timestamp);
+                // older saves end here
+                let mut clock_memory = Vec::new();
+                timestamp_save_file.read_to_end(&mut clock_memory)?;
+                cartridge.set_clock_memory(&clock_memory);
             }
         }
     }
@@ -259,6 +761,7 @@ fn save_timestamp_data<T: Write + Seek>(
     let mut rtc_data = rtc_data.to_bytes().to_vec();
     let mut rtc_last_time_data = rtc_last_time.to_ne_bytes().to_vec();
     rtc_data.append(&mut rtc_last_time_data);
+    rtc_data.extend_from_slice(cartridge.get_clock_memory());
     timestamp_save_file.seek(SeekFrom::Start(0))?;
     timestamp_save_file.write_all(&rtc_data)?;
     Ok(())
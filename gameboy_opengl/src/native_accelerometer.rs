@@ -0,0 +1,27 @@
+use gameboy_core::Accelerometer;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// the tilt follows the mouse, the center of the window is level
+// and the edges are tilted by 1g
+pub struct NativeAccelerometer {
+    tilt: Rc<Cell<(f32, f32)>>,
+}
+
+impl NativeAccelerometer {
+    pub fn new(tilt: Rc<Cell<(f32, f32)>>) -> NativeAccelerometer {
+        NativeAccelerometer { tilt }
+    }
+}
+
+impl Accelerometer for NativeAccelerometer {
+    fn get_acceleration(&self) -> (f32, f32) {
+        self.tilt.get()
+    }
+}
+
+pub fn mouse_position_to_tilt(x: i32, y: i32, width: u32, height: u32) -> (f32, f32) {
+    let tilt_x = (x as f32 / width as f32) * 2.0 - 1.0;
+    let tilt_y = (y as f32 / height as f32) * 2.0 - 1.0;
+    (tilt_x.clamp(-1.0, 1.0), tilt_y.clamp(-1.0, 1.0))
+}
@@ -0,0 +1,62 @@
+use gameboy_core::Rumble;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// games control the strength of the rumble by switching the motor on and off
+// very quickly, so we keep track of how long it was on for during each frame
+pub struct MotorState {
+    on: bool,
+    last_change: u64,
+    on_cycles: u64,
+    frame_start: u64,
+}
+
+impl MotorState {
+    pub fn new() -> MotorState {
+        MotorState {
+            on: false,
+            last_change: 0,
+            on_cycles: 0,
+            frame_start: 0,
+        }
+    }
+
+    fn set_on(&mut self, on: bool, cycle_count: u64) {
+        if self.on {
+            self.on_cycles += cycle_count - self.last_change;
+        }
+        self.on = on;
+        self.last_change = cycle_count;
+    }
+
+    // the fraction of the time since the last call that the motor was on for
+    pub fn take_strength(&mut self, cycle_count: u64) -> f32 {
+        let on = self.on;
+        self.set_on(on, cycle_count);
+        let elapsed = cycle_count - self.frame_start;
+        let strength = if elapsed > 0 {
+            self.on_cycles as f32 / elapsed as f32
+        } else {
+            0.0
+        };
+        self.on_cycles = 0;
+        self.frame_start = cycle_count;
+        strength
+    }
+}
+
+pub struct NativeRumble {
+    motor_state: Rc<RefCell<MotorState>>,
+}
+
+impl NativeRumble {
+    pub fn new(motor_state: Rc<RefCell<MotorState>>) -> NativeRumble {
+        NativeRumble { motor_state }
+    }
+}
+
+impl Rumble for NativeRumble {
+    fn set_rumble(&mut self, on: bool, cycle_count: u64) {
+        self.motor_state.borrow_mut().set_on(on, cycle_count);
+    }
+}
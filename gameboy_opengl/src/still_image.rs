@@ -0,0 +1,56 @@
+use gameboy_core::{ImageSource, CAMERA_HEIGHT, CAMERA_WIDTH};
+use std::fs::File;
+use std::path::Path;
+
+// a png image scaled to the camera's sensor, every capture sees the same picture
+pub struct StillImage {
+    pixels: Vec<u8>,
+}
+
+impl StillImage {
+    pub fn from_png(path: &Path) -> Result<StillImage, String> {
+        let file = File::open(path).map_err(|e| format!("{:?}", e))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info().map_err(|e| format!("{:?}", e))?;
+        let mut buffer = vec![0; info.buffer_size()];
+        reader
+            .next_frame(&mut buffer)
+            .map_err(|e| format!("{:?}", e))?;
+
+        let bytes_per_sample = match info.bit_depth {
+            png::BitDepth::Sixteen => 2,
+            _ => 1,
+        };
+        let bytes_per_pixel = info.line_size / info.width as usize;
+        let samples = bytes_per_pixel / bytes_per_sample;
+        let sample = |x: usize, y: usize, i: usize| -> u32 {
+            // for 16 bit images, the high byte comes first
+            u32::from(buffer[y * info.line_size + x * bytes_per_pixel + i * bytes_per_sample])
+        };
+
+        let mut pixels = vec![0; CAMERA_WIDTH * CAMERA_HEIGHT];
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let image_x = x * info.width as usize / CAMERA_WIDTH;
+                let image_y = y * info.height as usize / CAMERA_HEIGHT;
+                let brightness = if samples >= 3 {
+                    (sample(image_x, image_y, 0)
+                        + sample(image_x, image_y, 1)
+                        + sample(image_x, image_y, 2))
+                        / 3
+                } else {
+                    sample(image_x, image_y, 0)
+                };
+                pixels[y * CAMERA_WIDTH + x] = brightness as u8;
+            }
+        }
+        Ok(StillImage { pixels })
+    }
+}
+
+impl ImageSource for StillImage {
+    fn capture(&mut self, pixels: &mut [u8]) {
+        pixels.copy_from_slice(&self.pixels);
+    }
+}
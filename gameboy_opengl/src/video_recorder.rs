@@ -0,0 +1,136 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 144;
+// the LCD draws a frame every 70224 clocks, about 59.73 frames per second
+const CYCLES_PER_FRAME: u64 = 70224;
+// vblanks are only seen after the instruction that caused them, so a frame
+// has to be this late before the LCD is taken to be off
+const LCD_OFF_CYCLES: u64 = CYCLES_PER_FRAME + CYCLES_PER_FRAME / 2;
+const Y4M_HEADER: &[u8] = b"YUV4MPEG2 W160 H144 F4194304:70224 Ip A1:1 C444\n";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VideoFormat {
+    Y4m,
+    Png,
+}
+
+enum FrameOutput {
+    Y4m(BufWriter<File>),
+    Png(PathBuf),
+}
+
+// writes a frame on every vblank and repeats the last one while the LCD is off,
+// so the video stays in sync with the audio no matter how fast the host runs
+pub struct VideoRecorder {
+    output: FrameOutput,
+    last_frame: Vec<u8>,
+    frames_written: u64,
+    last_frame_cycle: u64,
+}
+
+impl VideoRecorder {
+    // y4m recordings go into recording_path.y4m,
+    // png recordings into numbered files in the recording_path directory
+    pub fn new(
+        format: VideoFormat,
+        recording_path: &Path,
+        frame_buffer: &[u8],
+        cycle_count: u64,
+    ) -> std::io::Result<VideoRecorder> {
+        let output = match format {
+            VideoFormat::Y4m => {
+                let mut file_name = recording_path.as_os_str().to_owned();
+                file_name.push(".y4m");
+                let mut file = BufWriter::new(File::create(file_name)?);
+                file.write_all(Y4M_HEADER)?;
+                FrameOutput::Y4m(file)
+            }
+            VideoFormat::Png => {
+                fs::create_dir_all(recording_path)?;
+                FrameOutput::Png(recording_path.to_path_buf())
+            }
+        };
+        Ok(VideoRecorder {
+            output,
+            last_frame: frame_buffer.to_vec(),
+            frames_written: 0,
+            last_frame_cycle: cycle_count,
+        })
+    }
+
+    // called on every vblank, with the frame that was just finished
+    pub fn frame_finished(&mut self, frame_buffer: &[u8], cycle_count: u64) -> std::io::Result<()> {
+        self.last_frame.copy_from_slice(frame_buffer);
+        self.last_frame_cycle = cycle_count;
+        self.write_frame()
+    }
+
+    // while the LCD is off no frames get finished, so the last one
+    // is repeated at the LCD's frame rate
+    pub fn update(&mut self, cycle_count: u64) -> std::io::Result<()> {
+        while cycle_count >= self.last_frame_cycle + LCD_OFF_CYCLES {
+            self.write_frame()?;
+            self.last_frame_cycle += CYCLES_PER_FRAME;
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self) -> std::io::Result<()> {
+        match self.output {
+            FrameOutput::Y4m(ref mut file) => {
+                file.write_all(b"FRAME\n")?;
+                file.write_all(&rgb_to_yuv444(&self.last_frame))?;
+            }
+            FrameOutput::Png(ref directory) => {
+                let frame_path = directory.join(format!("{:06}.png", self.frames_written));
+                write_png(&frame_path, &self.last_frame)?;
+            }
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> std::io::Result<()> {
+        if let FrameOutput::Y4m(mut file) = self.output {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// planar BT.601 studio range, which is what y4m players expect
+fn rgb_to_yuv444(frame_buffer: &[u8]) -> Vec<u8> {
+    let pixels = WIDTH * HEIGHT;
+    let mut planes = vec![0; pixels * 3];
+    for (i, rgb) in frame_buffer.chunks(3).enumerate() {
+        let r = f32::from(rgb[0]);
+        let g = f32::from(rgb[1]);
+        let b = f32::from(rgb[2]);
+        let y = 16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0;
+        let u = 128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0;
+        let v = 128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0;
+        planes[i] = y.round() as u8;
+        planes[pixels + i] = u.round() as u8;
+        planes[pixels * 2 + i] = v.round() as u8;
+    }
+    planes
+}
+
+// frame_buffer is 160x144 RGB24, like the screen's frame buffer.
+// io::Error::other is newer than the rust versions we build with
+#[allow(clippy::io_other_error)]
+pub fn write_png(path: &Path, frame_buffer: &[u8]) -> std::io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, WIDTH as u32, HEIGHT as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    writer
+        .write_image_data(frame_buffer)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
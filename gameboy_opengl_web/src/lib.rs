@@ -11,7 +11,7 @@ mod web_rtc;
 
 use crate::screen::Screen;
 use crate::web_rtc::WebRTC;
-use gameboy_core::{Button, Cartridge, ControllerEvent, Gameboy, Rtc, StepResult};
+use gameboy_core::{extract_rom, Button, Cartridge, ControllerEvent, Gameboy, Rtc, StepResult};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::mpsc;
@@ -139,6 +139,7 @@ impl EmulatorState {
             let mut rtc_bytes = rtc_data.to_bytes().to_vec();
             let mut last_timestamp_bytes = u64::to_ne_bytes(last_timestamp).to_vec();
             rtc_bytes.append(&mut last_timestamp_bytes);
+            rtc_bytes.extend_from_slice(self.gameboy.get_cartridge().get_clock_memory());
             let timestamp_data_str: String = rtc_bytes
                 .iter()
                 .map(|byte| format!("{:02x}", byte))
@@ -268,6 +269,7 @@ pub fn start(rom: Vec<u8>, dom_ids: DOMInfo) -> Result<(), String> {
         return h;
     };
     let rtc = Box::new(WebRTC::new());
+    let rom = extract_rom(rom, None)?;
     let mut gameboy = Gameboy::from_rom(rom, rtc)?;
     load_ram_save_data(gameboy.get_cartridge_mut());
     load_timestamp_data(gameboy.get_cartridge_mut());
@@ -405,9 +407,10 @@ fn load_timestamp_data(cartridge: &mut Cartridge) {
             .collect();
         let rtc = Rtc::from_bytes(&bytes[..5]);
         let mut timestamp_data = [0; 8];
-        timestamp_data.copy_from_slice(&bytes[5..]);
+        timestamp_data.copy_from_slice(&bytes[5..13]);
         let timestamp = u64::from_ne_bytes(timestamp_data);
         cartridge.set_last_timestamp(rtc, timestamp);
+        cartridge.set_clock_memory(&bytes[13..]);
     }
 }
 
@@ -443,7 +446,7 @@ fn emulate_a_single_frame(emulator_state: Rc<RefCell<EmulatorState>>) {
                     }
                     emulator_state.busy = false;
                 }
-                StepResult::Nothing => {}
+                StepResult::Nothing | StepResult::Breakpoint => {}
             };
         },
         0,
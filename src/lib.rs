@@ -9,7 +9,7 @@ extern crate stdweb;
 #[no_mangle]
 pub unsafe fn start(pointer: *mut u8, length: usize) {
     let rom = Vec::from_raw_parts(pointer, length, length);
-    gameboy_opengl::start(rom).unwrap();
+    gameboy_opengl::start(rom, gameboy_opengl::Options::default()).unwrap();
 }
 
 #[cfg(target_arch = "wasm32")]